@@ -1,3 +1,160 @@
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+
+/// Parses a model index like `3` or an inclusive range of them like `0-3`
+pub fn parse_model_range(value: &str) -> Result<RangeInclusive<usize>, String>
+{
+    let invalid = || format!("{:?} is not a model index or a range like 0-3", value);
+    let mut bounds = value.trim().splitn(2, '-');
+    let start: usize = bounds.next().unwrap_or_default().trim().parse().map_err(|_| invalid())?;
+    let end: usize = match bounds.next()
+    {
+        Some(end) => end.trim().parse().map_err(|_| invalid())?,
+        None => start
+    };
+    if end < start
+    {
+        return Err(invalid());
+    }
+    return Ok(start..=end);
+}
+
+
+/// Parses a positive frame rate
+pub fn parse_fps(value: &str) -> Result<f32, String>
+{
+    match value.trim().parse::<f32>()
+    {
+        Ok(fps) if fps.is_finite() && fps > 0.0 => {
+            return Ok(fps);
+        },
+        _ => {
+            return Err(format!("{:?} is not a positive frame rate", value));
+        }
+    }
+}
+
+/// Parses a palette index remapping like `3:7`
+pub fn parse_remap(value: &str) -> Result<(u8, u8), String>
+{
+    let invalid = || format!("{:?} is not a remapping of palette indices like 3:7", value);
+    let mut indices = value.trim().splitn(2, ':');
+    let from: u8 = indices.next().unwrap_or_default().trim().parse().map_err(|_| invalid())?;
+    let to: u8 = indices.next().ok_or_else(invalid)?.trim().parse().map_err(|_| invalid())?;
+    return Ok((from, to));
+}
+
+/// Parses a wall thickness of at least one voxel
+pub fn parse_wall(value: &str) -> Result<u32, String>
+{
+    match value.trim().parse::<u32>()
+    {
+        Ok(wall) if wall > 0 => {
+            return Ok(wall);
+        },
+        _ => {
+            return Err(format!("{:?} is not a wall thickness of at least one voxel", value));
+        }
+    }
+}
+
+/// Parses the column of a drain hole like `4:7`, or `lowest` for the lowest
+/// point of every cavity
+pub fn parse_drain(value: &str) -> Result<voxport::Drain, String>
+{
+    if value.trim() == "lowest"
+    {
+        return Ok(voxport::Drain::Lowest);
+    }
+    let invalid = || format!("{:?} is not a voxel column like 4:7 or \"lowest\"", value);
+    let mut coordinates = value.trim().splitn(2, ':');
+    let x: u8 = coordinates.next().unwrap_or_default().trim().parse().map_err(|_| invalid())?;
+    let y: u8 = coordinates.next().ok_or_else(invalid)?.trim().parse().map_err(|_| invalid())?;
+    return Ok(voxport::Drain::Column(x, y));
+}
+
+/// Parses a positive voxel size with its unit, like `0.5mm`, `2cm` or `0.1m`
+pub fn parse_voxel_size(value: &str) -> Result<voxport::VoxelSize, String>
+{
+    let invalid = || format!("{:?} is not a voxel size with a unit like 0.5mm, 2cm or 0.1m", value);
+    let value = value.trim();
+    let split = value.find(|c: char| c.is_ascii_alphabetic()).ok_or_else(invalid)?;
+    let length: f32 = value[..split].trim().parse().map_err(|_| invalid())?;
+    let unit = voxport::Unit::from_symbol(&value[split..]).ok_or_else(invalid)?;
+    if !(length.is_finite() && length > 0.0)
+    {
+        return Err(invalid());
+    }
+    return Ok(voxport::VoxelSize { length, unit });
+}
+
+/// Parses a pivot by name, or as a point in voxel coordinates like `4,0,2.5`
+pub fn parse_pivot(value: &str) -> Result<voxport::Pivot, String>
+{
+    match value.trim()
+    {
+        "magicavoxel" => {
+            return Ok(voxport::Pivot::MagicaVoxel);
+        },
+        "corner" => {
+            return Ok(voxport::Pivot::Corner);
+        },
+        "center" => {
+            return Ok(voxport::Pivot::Center);
+        },
+        "bottom-center" => {
+            return Ok(voxport::Pivot::BottomCenter);
+        },
+        point => {
+            let coordinates: Vec<f32> = point.split(',')
+                .map(|coordinate| coordinate.trim().parse::<f32>())
+                .collect::<std::result::Result<Vec<f32>, _>>()
+                .unwrap_or_default();
+            if coordinates.len() != 3 || !coordinates.iter().all(|coordinate| coordinate.is_finite())
+            {
+                return Err(format!("{:?} is not magicavoxel, corner, center, bottom-center or a point like 4,0,2.5", value));
+            }
+            return Ok(voxport::Pivot::Point([coordinates[0], coordinates[1], coordinates[2]]));
+        }
+    }
+}
+
+/// Parses a color group mapping, with a line like `0: 1-4, 9` for every group
+/// listing the palette indices that go into it. Lines starting with `#` are comments.
+pub fn parse_color_groups(text: &str) -> Result<BTreeMap<u8, usize>, String>
+{
+    let mut groups: BTreeMap<u8, usize> = BTreeMap::new();
+    for (number, line) in text.lines().enumerate()
+    {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#')
+        {
+            continue;
+        }
+
+        let invalid = |reason: &str| format!("line {}: {}", number + 1, reason);
+        let mut fields = line.splitn(2, ':');
+        let group: usize = fields.next().unwrap_or_default().trim().parse()
+            .map_err(|_| invalid("expected a group number before the colon"))?;
+        let indices = fields.next().ok_or_else(|| invalid("expected a colon after the group number"))?;
+        for value in indices.split(',').filter(|value| !value.trim().is_empty())
+        {
+            let range = parse_model_range(value).map_err(|_| invalid(&format!("{:?} is not a palette index or a range like 0-3", value.trim())))?;
+            for index in range
+            {
+                if index > u8::MAX as usize
+                {
+                    return Err(invalid(&format!("palette index {} is out of range", index)));
+                }
+                if groups.insert(index as u8, group).is_some_and(|other| other != group)
+                {
+                    return Err(invalid(&format!("palette index {} is already in another group", index)));
+                }
+            }
+        }
+    }
+    return Ok(groups);
+}
 
 pub fn new_app<'help>() -> clap::App<'help>
 {
@@ -7,16 +164,202 @@ pub fn new_app<'help>() -> clap::App<'help>
             .about("Exports in the STL (STereoLithography) format. Good for 3D Printing")
             .short('s')
             .long("stl")
-            .required(true)
-            .conflicts_with("dae")
         )
         .arg(
             clap::Arg::new("dae")
             .about("Exports in the Collada DAE format. Good for importing")
             .short('d')
             .long("dae")
+        )
+        .arg(
+            clap::Arg::new("gltf")
+            .about("Exports in the glTF 2.0 format, as a single binary file if the output ends in .glb. Good for game engines")
+            .short('g')
+            .long("gltf")
+        )
+        .arg(
+            clap::Arg::new("obj")
+            .about("Exports in the Wavefront OBJ format, with materials in an .mtl file next to it")
+            .long("obj")
+        )
+        .arg(
+            clap::Arg::new("ply")
+            .about("Exports in the Stanford PLY format with vertex colors")
+            .short('p')
+            .long("ply")
+        )
+        .group(
+            clap::ArgGroup::new("format")
+            .args(&["stl", "dae", "gltf", "obj", "ply"])
             .required(true)
-            .conflicts_with("stl")
+        )
+        .group(
+            clap::ArgGroup::new("animated-format")
+            .args(&["dae", "gltf"])
+        )
+        .arg(
+            clap::Arg::new("mesher")
+            .about("Meshing algorithm, greedy by default and naive for STL. Greedy merges coplanar faces, which leaves T-junctions where large faces meet small ones, naive emits every voxel face, manifold emits every voxel face and splits apart voxels touching only at an edge or corner, for a watertight surface slicers accept")
+            .short('m')
+            .long("mesher")
+            .takes_value(true)
+            .possible_values(&["greedy", "naive", "manifold"])
+        )
+        .arg(
+            clap::Arg::new("merge-colors")
+            .about("Lets the greedy mesher merge faces with different palette indices. Picks the greedy mesher for STL too, unless --mesher says otherwise")
+            .long("merge-colors")
+        )
+        .arg(
+            clap::Arg::new("voxel-size")
+            .about("Edge length of a voxel with its unit, like 0.5mm, 2cm or 0.1m, instead of one unit per voxel. COLLADA files name the unit, glTF files are converted to meters and the other formats hold plain numbers in the unit")
+            .long("voxel-size")
+            .takes_value(true)
+            .validator(parse_voxel_size)
+        )
+        .arg(
+            clap::Arg::new("pivot")
+            .about("Point of every model its scene node places and that it is exported around: magicavoxel for the center of the model size rounded down to whole voxels, corner for voxel 0,0,0 of the model, center or bottom-center of the box around its voxels, or a point in voxel coordinates like 4,0,2.5. Models of files without a scene graph have this point at the origin, so corner keeps them in the positive octant")
+            .long("pivot")
+            .takes_value(true)
+            .default_value("magicavoxel")
+            .validator(parse_pivot)
+        )
+        .arg(
+            clap::Arg::new("fill-cavities")
+            .about("Fills sealed cavities inside the models, which are left hollow by default, dropping the faces of their walls")
+            .long("fill-cavities")
+        )
+        .arg(
+            clap::Arg::new("hollow")
+            .about("Hollows the models for 3D printing, keeping walls this many voxels thick around the cavity. The STL holds both the outer and the inner shell")
+            .long("hollow")
+            .takes_value(true)
+            .requires("stl")
+            .validator(parse_wall)
+        )
+        .arg(
+            clap::Arg::new("drain")
+            .about("Drills drain holes down from the cavity of hollowed models, in the voxel columns of a comma separated list like 4:7,10:2, or at the lowest point of every cavity with \"lowest\"")
+            .long("drain")
+            .takes_value(true)
+            .multiple(true)
+            .use_delimiter(true)
+            .requires("hollow")
+            .validator(parse_drain)
+        )
+        .arg(
+            clap::Arg::new("texture")
+            .about("Maps the faces onto the palette, written as a PNG texture next to the output, instead of a material per palette index (DAE, OBJ and glTF). The texture is a 256x1 strip or a 16x16 grid")
+            .long("texture")
+            .takes_value(true)
+            .possible_values(&["strip", "grid"])
+            .conflicts_with_all(&["stl", "ply", "merge-colors"])
+        )
+        .arg(
+            clap::Arg::new("ascii")
+            .about("Writes the text variant of the export format instead of the binary one (STL and PLY)")
+            .short('a')
+            .long("ascii")
+        )
+        .arg(
+            clap::Arg::new("stl-color")
+            .about("Stores the palette color of every facet of a binary STL in the convention of VisCAM and SolidView or of Materialise")
+            .long("stl-color")
+            .takes_value(true)
+            .possible_values(&["viscam", "materialise"])
+            .requires("stl")
+            .conflicts_with("ascii")
+        )
+        .arg(
+            clap::Arg::new("palette")
+            .about("Replaces the palette of the input with the colors of a JASC-PAL (.pal), GIMP (.gpl) or PNG palette. PNG files 16 pixels high made of 16x16 swatches of one color give a color per swatch, any other PNG a color per pixel, read row by row")
+            .long("palette")
+            .takes_value(true)
+        )
+        .arg(
+            clap::Arg::new("remap")
+            .about("Moves voxels from one palette index to another before meshing, as a comma separated list like 3:7,4:8")
+            .long("remap")
+            .takes_value(true)
+            .multiple(true)
+            .use_delimiter(true)
+            .validator(parse_remap)
+        )
+        .arg(
+            clap::Arg::new("models")
+            .about("Only exports the models with these indices, as a comma separated list of indices and ranges like 0-3")
+            .long("models")
+            .takes_value(true)
+            .multiple(true)
+            .use_delimiter(true)
+            .validator(parse_model_range)
+        )
+        .arg(
+            clap::Arg::new("layers")
+            .about("Only exports the nodes on these layers, by name or id")
+            .long("layers")
+            .takes_value(true)
+            .multiple(true)
+            .use_delimiter(true)
+        )
+        .arg(
+            clap::Arg::new("nodes")
+            .about("Only exports the nodes with these names and everything below them")
+            .long("nodes")
+            .takes_value(true)
+            .multiple(true)
+            .use_delimiter(true)
+        )
+        .arg(
+            clap::Arg::new("include-hidden")
+            .about("Exports hidden nodes and the nodes on hidden layers, which are skipped otherwise")
+            .long("include-hidden")
+        )
+        .arg(
+            clap::Arg::new("split")
+            .about("Writes every model, layer or named node to a file of its own instead of one file")
+            .long("split")
+            .takes_value(true)
+            .possible_values(&["model", "layer", "node"])
+        )
+        .arg(
+            clap::Arg::new("name-template")
+            .about("File name of every part when splitting or frame of an animation sequence, next to the output. {stem} and {ext} come from the output file, {index} and {name} from the part")
+            .long("name-template")
+            .takes_value(true)
+            .default_value("{stem}_{index}.{ext}")
+        )
+        .arg(
+            clap::Arg::new("animation")
+            .about("Treats the models as frames of an animation, exported as a single animated file (DAE and glTF only) or as a sequence of files named by --name-template")
+            .long("animation")
+            .takes_value(true)
+            .possible_values(&["single", "sequence"])
+            .requires_if("single", "animated-format")
+            .conflicts_with("split")
+        )
+        .arg(
+            clap::Arg::new("fps")
+            .about("Frame rate of animations")
+            .long("fps")
+            .takes_value(true)
+            .default_value("10")
+            .validator(parse_fps)
+        )
+        .arg(
+            clap::Arg::new("split-colors")
+            .about("Writes the voxels of every palette index to an STL of its own named {stem}_color{index}.stl next to the output, all in the same coordinate frame. Every part is a closed body, so the greedy mesher is not allowed")
+            .long("split-colors")
+            .requires("stl")
+            .conflicts_with_all(&["split", "animation", "merge-colors"])
+        )
+        .arg(
+            clap::Arg::new("color-groups")
+            .about("File grouping palette indices into the parts of --split-colors, with a line like \"0: 1-4, 9\" per part. Parts of groups are named {stem}_group{number}.stl")
+            .long("color-groups")
+            .takes_value(true)
+            .requires("split-colors")
         )
         .arg(
             clap::Arg::new("input")
@@ -32,6 +375,37 @@ pub fn new_app<'help>() -> clap::App<'help>
             .long("output")
             .takes_value(true)
         )
+        .setting(clap::AppSettings::SubcommandsNegateReqs)
+        .subcommand(
+            clap::App::new("palette")
+            .about("Exports the palette of the input file")
+            .arg(
+                clap::Arg::new("format")
+                .about("Palette format, picked from the extension of the output file by default: JASC-PAL (.pal), GIMP (.gpl), Adobe color table (.act), a list of hex values (.hex) or a PNG swatch strip (.png)")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["jasc", "gpl", "act", "hex", "png"])
+            )
+            .arg(
+                clap::Arg::new("used")
+                .about("Only exports the colors voxels use")
+                .long("used")
+            )
+            .arg(
+                clap::Arg::new("input")
+                .about("Input MagicaVoxel file to read the palette from")
+                .short('i')
+                .long("input")
+                .takes_value(true)
+            )
+            .arg(
+                clap::Arg::new("output")
+                .about("Output palette file")
+                .short('o')
+                .long("output")
+                .takes_value(true)
+            )
+        )
     ;
     return app;
 }
\ No newline at end of file
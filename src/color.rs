@@ -0,0 +1,67 @@
+/// An RGBA color unpacked from a MagicaVoxel palette entry
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Color
+{
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8
+}
+
+impl Color
+{
+    /// Looks up entry `index` of a `DotVoxData.palette`, falling back to opaque white
+    pub fn from_palette(palette: &[u32], index: u8) -> Self
+    {
+        return palette.get(index as usize).copied().unwrap_or(0xFF_FF_FF_FF).into();
+    }
+
+    /// Channels scaled to `0.0..=1.0` and converted from sRGB to linear light
+    pub fn to_linear(&self) -> [f32; 4]
+    {
+        let linear = |channel: u8| {
+            let c = channel as f32 / 255.0;
+            if c <= 0.04045
+            {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+
+        return [linear(self.r), linear(self.g), linear(self.b), self.a as f32 / 255.0];
+    }
+}
+
+impl From<u32> for Color
+{
+    fn from(src: u32) -> Self 
+    { 
+        let r = ((src & 0x00_00_00_FF) >> 0) as u8;
+        let g = ((src & 0x00_00_FF_00) >> 8) as u8;
+        let b = ((src & 0x00_FF_00_00) >> 16) as u8;
+        let a = ((src & 0xFF_00_00_00) >> 24) as u8;
+        Self {
+            r,
+            g,
+            b,
+            a
+        } 
+    }
+}
+
+impl Into<u32> for Color
+{
+    fn into(self) -> u32
+    {
+        (self.a as u32) << 24 | (self.b as u32) << 16 | (self.g as u32) << 8 | self.r as u32
+    }
+}
+
+impl Into<String> for Color
+{
+    fn into(self) -> String
+    {
+        format!("{} {} {}", self.r, self.g, self.b)
+    }
+}
@@ -0,0 +1,599 @@
+//! COLLADA 1.4.1 export.
+//!
+//! Geometry sources, vertices and the asset block are written through
+//! `collada_io`, while images, effects, materials, colored triangles and
+//! material bindings, which `collada_io` can't express yet, are written directly.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+
+use collada_io::geometry::{Accessor, FloatArray, Source, Vertices};
+use collada_io::io::XmlWrite;
+use collada_io::util::{write_end_element, write_start_element, write_text_element, write_vec_element};
+use xml::attribute::Attribute;
+use xml::writer::{EmitterConfig, EventWriter, XmlEvent};
+
+use crate::color::Color;
+use crate::error::{Result, VoxportError};
+use crate::material::{palette_materials, VoxMaterial};
+use crate::mesh::{convert_model, index_triangles, scale_welded_positions, IndexedTriangle, MeshOptions, Normal, Vertex};
+use crate::palette::{material_name, palette_texture, PaletteTexture, TextureLayout, TEXTURE_MATERIAL};
+use crate::scene::{model_pivot, Scene, SceneNode};
+
+/// A mesh ready to be written as a `<geometry>`
+struct DaeGeometry
+{
+    id: String,
+    name: String,
+    sources: Vec<Source>,
+    vertices: Vertices,
+    vertices_source: String,
+    normals_source: String,
+    colors_source: String,
+    texcoords_source: Option<String>,
+    /// Palette indices used by the triangles
+    colors: BTreeSet<u8>,
+    /// Interleaved vertex, normal, color and texture coordinate indices of the
+    /// triangles of each material symbol
+    triangle_groups: BTreeMap<String, Vec<usize>>
+}
+
+fn attributes<'a>(pairs: &[(&'a str, &'a str)]) -> Vec<Attribute<'a>>
+{
+    return pairs.iter().map(|(name, value)| Attribute {
+        name: (*name).into(),
+        value
+    }).collect();
+}
+
+fn effect_id(material: &str) -> String
+{
+    return format!("{}-effect", material);
+}
+
+fn material_id(material: &str) -> String
+{
+    return format!("{}-material", material);
+}
+
+const TEXTURE_IMAGE_ID: &str = "palette-image";
+/// Name the texture coordinates are bound by between effect and geometry
+const TEXCOORD_SET: &str = "UVMap";
+
+fn source(id: &str, data: Vec<f32>, params: &[&str]) -> Source
+{
+    return Source {
+        id: id.to_string(),
+        float_array: FloatArray {
+            id: format!("{}-array", id),
+            data
+        },
+        accessor: Accessor {
+            params: params.iter().map(|param| param.to_string()).collect()
+        }
+    };
+}
+
+/// Material symbol of palette index `index`. Textured palette indices with a
+/// plain diffuse material share a single material.
+fn material_symbol(index: u8, material: &VoxMaterial, textured: bool) -> String
+{
+    if textured && material.is_default()
+    {
+        return TEXTURE_MATERIAL.to_string();
+    }
+    return material_name(index);
+}
+
+/// Builds the geometry of a model, with every id prefixed by `name` and positions
+/// multiplied by `scale`. With a `texture` the triangles get texture coordinates.
+fn build_geometry(name: &str, triangles: &[crate::mesh::Triangle], scale: f32, palette: &[u32], materials: &[VoxMaterial], texture: Option<TextureLayout>) -> DaeGeometry
+{
+    let mut vertices: Vec<Vertex> = Vec::new();
+    let mut normals: Vec<Normal> = Vec::new();
+    let mut indexed_triangles: Vec<IndexedTriangle> = Vec::new();
+    index_triangles(triangles, &mut vertices, &mut normals, &mut indexed_triangles);
+    scale_welded_positions(&mut vertices, scale);
+
+    // One COLOR and TEXCOORD entry per palette index used by the mesh
+    let mut color_offsets: BTreeMap<u8, usize> = BTreeMap::new();
+    let mut mesh_colors: Vec<f32> = Vec::new();
+    let mut mesh_texcoords: Vec<f32> = Vec::new();
+    let mut triangle_groups: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for idx_triangle in indexed_triangles
+    {
+        let color_offset = *color_offsets.entry(idx_triangle.color_index).or_insert_with(|| {
+            let color = Color::from_palette(palette, idx_triangle.color_index);
+            mesh_colors.extend_from_slice(&[
+                color.r as f32 / 255.0,
+                color.g as f32 / 255.0,
+                color.b as f32 / 255.0,
+                color.a as f32 / 255.0
+            ]);
+            match texture
+            {
+                Some(layout) => {
+                    // COLLADA counts T up from the bottom of the texture
+                    let uv = layout.uv(idx_triangle.color_index);
+                    mesh_texcoords.extend_from_slice(&[uv[0], 1.0 - uv[1]]);
+                },
+                None => {}
+            }
+            mesh_colors.len() / 4 - 1
+        });
+
+        let material = material_symbol(idx_triangle.color_index, &materials[idx_triangle.color_index as usize], texture.is_some());
+        let primitive = triangle_groups.entry(material).or_default();
+        for vertex in [idx_triangle.a, idx_triangle.b, idx_triangle.c].iter()
+        {
+            primitive.push(*vertex);
+            primitive.push(idx_triangle.normal_index);
+            primitive.push(color_offset);
+            if texture.is_some()
+            {
+                primitive.push(color_offset);
+            }
+        }
+    }
+
+    let mesh_positions: Vec<f32> = vertices.iter().flat_map(|vertex| vec![vertex.x, vertex.y, vertex.z]).collect();
+    let mesh_normals: Vec<f32> = normals.iter().flat_map(|normal| vec![normal.x, normal.y, normal.z]).collect();
+
+    let id = format!("{}-mesh", name);
+    let mut sources = vec! {
+        source(&format!("{}-positions", id), mesh_positions, &["X", "Y", "Z"]),
+        source(&format!("{}-normals", id), mesh_normals, &["X", "Y", "Z"]),
+        source(&format!("{}-colors", id), mesh_colors, &["R", "G", "B", "A"])
+    };
+    let mut texcoords_source: Option<String> = None;
+    if texture.is_some()
+    {
+        sources.push(source(&format!("{}-texcoords", id), mesh_texcoords, &["S", "T"]));
+        texcoords_source = Some(format!("#{}-texcoords", id));
+    }
+
+    return DaeGeometry {
+        sources,
+        vertices: Vertices {
+            id: format!("{}-vertices", id),
+            name: None,
+            source: format!("#{}-positions", id)
+        },
+        vertices_source: format!("#{}-vertices", id),
+        normals_source: format!("#{}-normals", id),
+        colors_source: format!("#{}-colors", id),
+        texcoords_source,
+        colors: color_offsets.keys().copied().collect(),
+        triangle_groups,
+        name: name.to_string(),
+        id
+    };
+}
+
+/// Writes the `<asset>` block. `collada_io` formats dates as RFC 2822, which the
+/// schema rejects, so the dates are written here as RFC 3339 `xs:dateTime`s,
+/// and it names the `name` attribute of `<unit>` "unit".
+fn write_asset<W: Write>(w: &mut EventWriter<W>, asset: &collada_io::meta::Asset) -> xml::writer::Result<()>
+{
+    write_start_element(w, "asset", &Vec::new())?;
+    for contributor in &asset.contributors
+    {
+        contributor.write(w)?;
+    }
+    write_text_element(w, "created", &asset.created.to_rfc3339(), &Vec::new())?;
+    write_text_element(w, "modified", &asset.modified.to_rfc3339(), &Vec::new())?;
+    let meter = asset.unit.meter.to_string();
+    write_start_element(w, "unit", &attributes(&[("name", &asset.unit.name), ("meter", &meter)]))?;
+    write_end_element(w, "unit")?;
+    match &asset.up_axis
+    {
+        Some(up_axis) => {
+            up_axis.write(w)?;
+        },
+        None => {
+
+        }
+    }
+    write_end_element(w, "asset")?;
+    Ok(())
+}
+
+fn write_image<W: Write>(w: &mut EventWriter<W>, texture: &PaletteTexture) -> xml::writer::Result<()>
+{
+    write_start_element(w, "image", &attributes(&[("id", TEXTURE_IMAGE_ID), ("name", TEXTURE_MATERIAL)]))?;
+    write_text_element(w, "init_from", &texture.uri, &Vec::new())?;
+    write_end_element(w, "image")?;
+    Ok(())
+}
+
+/// Writes a `<color>` or `<float>` wrapped in the shader parameter `name`
+fn write_parameter<W: Write>(w: &mut EventWriter<W>, name: &str, kind: &str, values: Vec<f32>) -> xml::writer::Result<()>
+{
+    write_start_element(w, name, &Vec::new())?;
+    write_vec_element(w, kind, &values, &attributes(&[("sid", name)]))?;
+    write_end_element(w, name)?;
+    Ok(())
+}
+
+/// Writes the effect of material `name`, a Lambert shader unless the material
+/// is metallic or glossy, which takes a Phong one. The diffuse color is `color`,
+/// or with `textured` the palette texture sampled without filtering so that
+/// neighbouring palette entries don't bleed in.
+fn write_effect<W: Write>(w: &mut EventWriter<W>, name: &str, color: Color, material: &VoxMaterial, textured: bool) -> xml::writer::Result<()>
+{
+    let id = effect_id(name);
+    write_start_element(w, "effect", &attributes(&[("id", &id)]))?;
+    write_start_element(w, "profile_COMMON", &Vec::new())?;
+    if textured
+    {
+        write_start_element(w, "newparam", &attributes(&[("sid", "palette-surface")]))?;
+        write_start_element(w, "surface", &attributes(&[("type", "2D")]))?;
+        write_text_element(w, "init_from", TEXTURE_IMAGE_ID, &Vec::new())?;
+        write_end_element(w, "surface")?;
+        write_end_element(w, "newparam")?;
+        write_start_element(w, "newparam", &attributes(&[("sid", "palette-sampler")]))?;
+        write_start_element(w, "sampler2D", &Vec::new())?;
+        write_text_element(w, "source", "palette-surface", &Vec::new())?;
+        write_text_element(w, "minfilter", "NEAREST", &Vec::new())?;
+        write_text_element(w, "magfilter", "NEAREST", &Vec::new())?;
+        write_end_element(w, "sampler2D")?;
+        write_end_element(w, "newparam")?;
+    }
+    write_start_element(w, "technique", &attributes(&[("sid", "common")]))?;
+    let shader = if material.metallic > 0.0 || material.roughness < 1.0 { "phong" } else { "lambert" };
+    write_start_element(w, shader, &Vec::new())?;
+
+    let rgb = [color.r as f32 / 255.0, color.g as f32 / 255.0, color.b as f32 / 255.0];
+    if material.emission > 0.0
+    {
+        write_parameter(w, "emission", "color", vec![rgb[0] * material.emission, rgb[1] * material.emission, rgb[2] * material.emission, 1.0])?;
+    }
+    if textured
+    {
+        write_start_element(w, "diffuse", &Vec::new())?;
+        write_start_element(w, "texture", &attributes(&[("texture", "palette-sampler"), ("texcoord", TEXCOORD_SET)]))?;
+        write_end_element(w, "texture")?;
+        write_end_element(w, "diffuse")?;
+    } else {
+        write_parameter(w, "diffuse", "color", vec![rgb[0], rgb[1], rgb[2], color.a as f32 / 255.0])?;
+    }
+    if shader == "phong"
+    {
+        // Dielectrics reflect about 4% of the light, metals reflect their own color
+        let specular: Vec<f32> = rgb.iter().map(|c| 0.04 + (c - 0.04) * material.metallic).chain(vec![1.0]).collect();
+        write_parameter(w, "specular", "color", specular)?;
+        write_parameter(w, "shininess", "float", vec![(1.0 - material.roughness) * 128.0])?;
+    }
+    if material.metallic > 0.0
+    {
+        write_parameter(w, "reflective", "color", vec![rgb[0], rgb[1], rgb[2], 1.0])?;
+        write_parameter(w, "reflectivity", "float", vec![material.metallic])?;
+    }
+    if material.transmission > 0.0 || material.opacity < 1.0
+    {
+        write_start_element(w, "transparent", &attributes(&[("opaque", "A_ONE")]))?;
+        write_vec_element(w, "color", &vec![1.0, 1.0, 1.0, 1.0], &attributes(&[("sid", "transparent")]))?;
+        write_end_element(w, "transparent")?;
+        write_parameter(w, "transparency", "float", vec![material.opacity * (1.0 - material.transmission)])?;
+    }
+    if material.transmission > 0.0
+    {
+        write_parameter(w, "index_of_refraction", "float", vec![material.ior])?;
+    }
+
+    write_end_element(w, shader)?;
+    write_end_element(w, "technique")?;
+    write_end_element(w, "profile_COMMON")?;
+    write_end_element(w, "effect")?;
+    Ok(())
+}
+
+fn write_material<W: Write>(w: &mut EventWriter<W>, name: &str) -> xml::writer::Result<()>
+{
+    let id = material_id(name);
+    let effect_url = format!("#{}", effect_id(name));
+    write_start_element(w, "material", &attributes(&[("id", &id), ("name", name)]))?;
+    write_start_element(w, "instance_effect", &attributes(&[("url", &effect_url)]))?;
+    write_end_element(w, "instance_effect")?;
+    write_end_element(w, "material")?;
+    Ok(())
+}
+
+fn write_input<W: Write>(w: &mut EventWriter<W>, semantic: &str, source: &str, offset: &str) -> xml::writer::Result<()>
+{
+    write_start_element(w, "input", &attributes(&[("semantic", semantic), ("source", source), ("offset", offset)]))?;
+    write_end_element(w, "input")?;
+    Ok(())
+}
+
+fn write_geometry<W: Write>(w: &mut EventWriter<W>, geometry: &DaeGeometry) -> xml::writer::Result<()>
+{
+    write_start_element(w, "geometry", &attributes(&[("id", &geometry.id), ("name", &geometry.name)]))?;
+    write_start_element(w, "mesh", &Vec::new())?;
+    for source in &geometry.sources
+    {
+        source.write(w)?;
+    }
+    geometry.vertices.write(w)?;
+
+    let stride = if geometry.texcoords_source.is_some() { 4 } else { 3 };
+    for (material, primitive) in &geometry.triangle_groups
+    {
+        let count = (primitive.len() / (3 * stride)).to_string();
+        write_start_element(w, "triangles", &attributes(&[("material", material), ("count", &count)]))?;
+        write_input(w, "VERTEX", &geometry.vertices_source, "0")?;
+        write_input(w, "NORMAL", &geometry.normals_source, "1")?;
+        write_input(w, "COLOR", &geometry.colors_source, "2")?;
+        match &geometry.texcoords_source
+        {
+            Some(texcoords_source) => {
+                write_start_element(w, "input", &attributes(&[("semantic", "TEXCOORD"), ("source", texcoords_source), ("offset", "3"), ("set", "0")]))?;
+                write_end_element(w, "input")?;
+            },
+            None => {}
+        }
+        write_vec_element(w, "p", primitive, &Vec::new())?;
+        write_end_element(w, "triangles")?;
+    }
+
+    write_end_element(w, "mesh")?;
+    write_end_element(w, "geometry")?;
+    Ok(())
+}
+
+/// Writes `node` and its children as nested nodes, binding every material
+/// symbol the geometry of a shape uses to its material
+fn write_node<W: Write>(w: &mut EventWriter<W>, node: &SceneNode, geometries: &BTreeMap<usize, DaeGeometry>, scale: f32, id_prefix: &str) -> xml::writer::Result<()>
+{
+    let id = format!("{}Node-{}", id_prefix, node.id);
+    let name = node.label();
+    write_start_element(w, "node", &attributes(&[("id", &id), ("name", &name), ("type", "NODE")]))?;
+    write_vec_element(w, "matrix", &node.transform.to_scaled_matrix(scale).to_vec(), &attributes(&[("sid", "transform")]))?;
+    match node.model
+    {
+        Some(model) => {
+            let geometry = &geometries[&model];
+            let geometry_url = format!("#{}", geometry.id);
+            write_start_element(w, "instance_geometry", &attributes(&[("url", &geometry_url), ("name", &name)]))?;
+            // The schema requires at least one instance_material inside a bind_material
+            if !geometry.triangle_groups.is_empty()
+            {
+                write_start_element(w, "bind_material", &Vec::new())?;
+                write_start_element(w, "technique_common", &Vec::new())?;
+                for symbol in geometry.triangle_groups.keys()
+                {
+                    let target = format!("#{}", material_id(symbol));
+                    write_start_element(w, "instance_material", &attributes(&[("symbol", symbol), ("target", &target)]))?;
+                    if geometry.texcoords_source.is_some()
+                    {
+                        write_start_element(w, "bind_vertex_input", &attributes(&[("semantic", TEXCOORD_SET), ("input_semantic", "TEXCOORD"), ("input_set", "0")]))?;
+                        write_end_element(w, "bind_vertex_input")?;
+                    }
+                    write_end_element(w, "instance_material")?;
+                }
+                write_end_element(w, "technique_common")?;
+                write_end_element(w, "bind_material")?;
+            }
+            write_end_element(w, "instance_geometry")?;
+        },
+        None => {}
+    }
+    for child in &node.children
+    {
+        write_node(w, child, geometries, scale, id_prefix)?;
+    }
+    write_end_element(w, "node")?;
+    Ok(())
+}
+
+/// Writes the animation showing frame `index` of `count` while it is on, by
+/// scaling its node down to nothing the rest of the time
+fn write_frame_animation<W: Write>(w: &mut EventWriter<W>, index: usize, count: usize, fps: f32) -> xml::writer::Result<()>
+{
+    let id = format!("Frame-{}-visibility", index);
+    // The extra key at the end holds the last frame for its duration
+    let times: Vec<f32> = (0..=count).map(|key| key as f32 / fps).collect();
+    let scales: Vec<f32> = (0..=count)
+        .flat_map(|key| {
+            let shown = if key.min(count - 1) == index { 1.0 } else { 0.0 };
+            vec![shown; 3]
+        })
+        .collect();
+
+    write_start_element(w, "animation", &attributes(&[("id", &id)]))?;
+    let input_id = format!("{}-input", id);
+    let output_id = format!("{}-output", id);
+    source(&input_id, times, &["TIME"]).write(w)?;
+    source(&output_id, scales, &["X", "Y", "Z"]).write(w)?;
+
+    // collada_io only writes float sources, so the interpolation names are written here
+    let interpolation_id = format!("{}-interpolation", id);
+    let names_id = format!("{}-array", interpolation_id);
+    let names_url = format!("#{}", names_id);
+    let key_count = (count + 1).to_string();
+    write_start_element(w, "source", &attributes(&[("id", &interpolation_id)]))?;
+    write_vec_element(w, "Name_array", &vec!["STEP"; count + 1], &attributes(&[("id", &names_id), ("count", &key_count)]))?;
+    write_start_element(w, "technique_common", &Vec::new())?;
+    write_start_element(w, "accessor", &attributes(&[("source", &names_url), ("count", &key_count), ("stride", "1")]))?;
+    write_start_element(w, "param", &attributes(&[("name", "INTERPOLATION"), ("type", "name")]))?;
+    write_end_element(w, "param")?;
+    write_end_element(w, "accessor")?;
+    write_end_element(w, "technique_common")?;
+    write_end_element(w, "source")?;
+
+    let sampler_id = format!("{}-sampler", id);
+    write_start_element(w, "sampler", &attributes(&[("id", &sampler_id)]))?;
+    for (semantic, source_id) in [("INPUT", &input_id), ("OUTPUT", &output_id), ("INTERPOLATION", &interpolation_id)].iter()
+    {
+        let source_url = format!("#{}", source_id);
+        write_start_element(w, "input", &attributes(&[("semantic", semantic), ("source", &source_url)]))?;
+        write_end_element(w, "input")?;
+    }
+    write_end_element(w, "sampler")?;
+
+    let sampler_url = format!("#{}", sampler_id);
+    let target = format!("Frame-{}/scale", index);
+    write_start_element(w, "channel", &attributes(&[("source", &sampler_url), ("target", &target)]))?;
+    write_end_element(w, "channel")?;
+    write_end_element(w, "animation")?;
+    Ok(())
+}
+
+/// Writes the COLLADA document for a single scene, or with an `fps` for an
+/// animation showing one of `frames` after the other
+fn write_document<W: Write>(in_data: &dot_vox::DotVoxData, frames: &[Scene], fps: Option<f32>, options: &MeshOptions, writer: &mut W) -> Result<()>
+{
+    if fps.is_some_and(|fps| !(fps.is_finite() && fps > 0.0))
+    {
+        return Err(VoxportError::Export("animation frame rate must be positive".to_string()));
+    }
+
+    let texture = palette_texture(options)?;
+    let scale = options.position_scale();
+    let materials = palette_materials(in_data);
+    let placed: BTreeSet<usize> = frames.iter().flat_map(|frame| frame.models()).collect();
+    let mut geometries: BTreeMap<usize, DaeGeometry> = BTreeMap::new();
+    for i in placed
+    {
+        let model = &in_data.models[i];
+        let triangles = model_pivot(&model.size).apply_triangles(&convert_model(model, options)?);
+        geometries.insert(i, build_geometry(&format!("Voxel-{}", i), &triangles, scale, &in_data.palette, &materials, texture.map(|texture| texture.layout)));
+    }
+
+    let used: BTreeSet<u8> = geometries.values().flat_map(|geometry| geometry.colors.iter().copied()).collect();
+    // Every material symbol in use, with a palette index whose color and material it shows
+    let mut symbols: BTreeMap<String, u8> = BTreeMap::new();
+    for index in used
+    {
+        symbols.entry(material_symbol(index, &materials[index as usize], texture.is_some())).or_insert(index);
+    }
+
+    let mut w = EmitterConfig::new().perform_indent(true).create_writer(writer);
+    w.write(XmlEvent::StartDocument {
+        version: xml::common::XmlVersion::Version10,
+        encoding: Some("utf-8"),
+        standalone: None
+    })?;
+    write_start_element(&mut w, "COLLADA", &attributes(&[
+        ("xmlns", collada_io::collada::COLLADA_XMLNS),
+        ("version", collada_io::collada::COLLADA_VERSION),
+        ("xmlns:xsi", collada_io::collada::COLLADA_XMLNS_XSI)
+    ]))?;
+    let mut asset = collada_io::meta::Asset {
+        up_axis: Some(collada_io::meta::UpAxis::ZUp),
+        ..collada_io::meta::Asset::default()
+    };
+    match options.voxel_size
+    {
+        Some(voxel_size) => {
+            asset.unit = collada_io::meta::Unit {
+                name: voxel_size.unit.name().to_string(),
+                meter: voxel_size.unit.meters()
+            };
+        },
+        None => {}
+    }
+    write_asset(&mut w, &asset)?;
+
+    match texture
+    {
+        Some(texture) if !symbols.is_empty() => {
+            write_start_element(&mut w, "library_images", &Vec::new())?;
+            write_image(&mut w, texture)?;
+            write_end_element(&mut w, "library_images")?;
+        },
+        _ => {}
+    }
+
+    if !symbols.is_empty()
+    {
+        write_start_element(&mut w, "library_effects", &Vec::new())?;
+        for (symbol, index) in &symbols
+        {
+            let color = Color::from_palette(&in_data.palette, *index);
+            write_effect(&mut w, symbol, color, &materials[*index as usize], texture.is_some())?;
+        }
+        write_end_element(&mut w, "library_effects")?;
+
+        write_start_element(&mut w, "library_materials", &Vec::new())?;
+        for symbol in symbols.keys()
+        {
+            write_material(&mut w, symbol)?;
+        }
+        write_end_element(&mut w, "library_materials")?;
+    }
+
+    // Libraries and visual scenes can't be empty, so a file without models only has an asset
+    if !geometries.is_empty()
+    {
+        write_start_element(&mut w, "library_geometries", &Vec::new())?;
+        for geometry in geometries.values()
+        {
+            write_geometry(&mut w, geometry)?;
+        }
+        write_end_element(&mut w, "library_geometries")?;
+
+        match fps
+        {
+            Some(fps) => {
+                write_start_element(&mut w, "library_animations", &Vec::new())?;
+                for index in 0..frames.len()
+                {
+                    write_frame_animation(&mut w, index, frames.len(), fps)?;
+                }
+                write_end_element(&mut w, "library_animations")?;
+            },
+            None => {}
+        }
+
+        write_start_element(&mut w, "library_visual_scenes", &Vec::new())?;
+        write_start_element(&mut w, "visual_scene", &attributes(&[("id", "Scene"), ("name", "Scene")]))?;
+        match fps
+        {
+            Some(_) => {
+                for (index, frame) in frames.iter().enumerate()
+                {
+                    let id = format!("Frame-{}", index);
+                    let shown: Vec<f32> = if index == 0 { vec![1.0; 3] } else { vec![0.0; 3] };
+                    write_start_element(&mut w, "node", &attributes(&[("id", &id), ("name", &id), ("type", "NODE")]))?;
+                    write_vec_element(&mut w, "scale", &shown, &attributes(&[("sid", "scale")]))?;
+                    write_node(&mut w, &frame.root, &geometries, scale, &format!("{}-", id))?;
+                    write_end_element(&mut w, "node")?;
+                }
+            },
+            None => {
+                for frame in frames
+                {
+                    write_node(&mut w, &frame.root, &geometries, scale, "")?;
+                }
+            }
+        }
+        write_end_element(&mut w, "visual_scene")?;
+        write_end_element(&mut w, "library_visual_scenes")?;
+
+        collada_io::scene::Scene {
+            visual_scenes: vec!{
+                "#Scene".to_string()
+            }
+        }.write(&mut w)?;
+    }
+
+    write_end_element(&mut w, "COLLADA")?;
+    return Ok(());
+}
+
+/// Meshes the models placed by `scene` and writes them as a COLLADA document,
+/// with a node for every transform node of `scene` and a material for every
+/// palette index in use. Palette indices with a plain diffuse material share a
+/// single one showing the palette texture, if there is one.
+pub fn convert_vox_dae<W: Write>(in_data: &dot_vox::DotVoxData, scene: &Scene, options: &MeshOptions, writer: &mut W) -> Result<()>
+{
+    return write_document(in_data, std::slice::from_ref(scene), None, options, writer);
+}
+
+/// Writes `frames` as a COLLADA animation playing at `fps` frames per second,
+/// like `convert_vox_dae` does for a single scene
+pub fn convert_animation_dae<W: Write>(in_data: &dot_vox::DotVoxData, frames: &[Scene], fps: f32, options: &MeshOptions, writer: &mut W) -> Result<()>
+{
+    return write_document(in_data, frames, Some(fps), options, writer);
+}
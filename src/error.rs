@@ -0,0 +1,113 @@
+use std::fmt;
+
+/// Everything that can go wrong while converting a `.vox` file
+#[derive(Debug)]
+pub enum VoxportError
+{
+    /// An input file is missing or can't be read
+    NoInput(std::io::Error),
+    /// Writing the output, or reading past the start of a file, failed
+    Io(std::io::Error),
+    /// The input is not a valid MagicaVoxel file
+    Parse(String),
+    /// A file other than the input, like a color group mapping, is not valid
+    InvalidInput(String),
+    /// The model selection given on the command line leaves nothing to export
+    EmptySelection,
+    /// The file parsed, but contains a model voxport can't convert
+    UnsupportedModel(String),
+    /// An exporter failed for a reason other than I/O
+    Export(String)
+}
+
+pub type Result<T> = std::result::Result<T, VoxportError>;
+
+impl VoxportError
+{
+    /// Process exit code for the error, following the BSD `sysexits.h` values
+    pub fn exit_code(&self) -> i32
+    {
+        match self
+        {
+            Self::Parse(_) | Self::InvalidInput(_) | Self::EmptySelection => 65, // EX_DATAERR
+            Self::NoInput(_) => 66, // EX_NOINPUT
+            Self::UnsupportedModel(_) => 69, // EX_UNAVAILABLE
+            Self::Export(_) => 70, // EX_SOFTWARE
+            Self::Io(_) => 74 // EX_IOERR
+        }
+    }
+}
+
+impl fmt::Display for VoxportError
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self
+        {
+            Self::NoInput(err) => write!(f, "cannot read input: {}", err),
+            Self::Io(err) => write!(f, "I/O error: {}", err),
+            Self::Parse(msg) => write!(f, "could not parse MagicaVoxel file: {}", msg),
+            Self::InvalidInput(msg) => write!(f, "invalid file: {}", msg),
+            Self::EmptySelection => write!(f, "no models selected for export, nothing written"),
+            Self::UnsupportedModel(msg) => write!(f, "unsupported model: {}", msg),
+            Self::Export(msg) => write!(f, "export failed: {}", msg)
+        }
+    }
+}
+
+impl std::error::Error for VoxportError
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)>
+    {
+        match self
+        {
+            Self::NoInput(err) | Self::Io(err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl From<std::io::Error> for VoxportError
+{
+    fn from(err: std::io::Error) -> Self
+    {
+        Self::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for VoxportError
+{
+    fn from(err: serde_json::Error) -> Self
+    {
+        if err.is_io()
+        {
+            return Self::Io(err.into());
+        }
+
+        return Self::Export(err.to_string());
+    }
+}
+
+impl From<xml::writer::Error> for VoxportError
+{
+    fn from(err: xml::writer::Error) -> Self
+    {
+        match err
+        {
+            xml::writer::Error::Io(err) => Self::Io(err),
+            err => Self::Export(err.to_string())
+        }
+    }
+}
+
+impl From<png::EncodingError> for VoxportError
+{
+    fn from(err: png::EncodingError) -> Self
+    {
+        match err
+        {
+            png::EncodingError::IoError(err) => Self::Io(err),
+            err => Self::Export(err.to_string())
+        }
+    }
+}
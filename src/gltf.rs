@@ -0,0 +1,614 @@
+//! glTF 2.0 export, either as a `.gltf` document next to a separate `.bin`
+//! buffer or as a single binary `.glb` file.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::Write;
+
+use serde_json::{json, Value};
+
+use crate::color::Color;
+use crate::error::{Result, VoxportError};
+use crate::material::{palette_materials, VoxMaterial};
+use crate::mesh::{convert_model, scale_welded_positions, MeshOptions, Triangle, Vertex};
+use crate::palette::{export_palette_texture, material_name, palette_texture, TextureLayout};
+use crate::scene::{model_pivot, Scene, SceneNode, Transform};
+use crate::weld::{lattice_key, LatticeKey};
+
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const UNSIGNED_INT: u32 = 5125;
+const FLOAT: u32 = 5126;
+const NEAREST: u32 = 9728;
+
+const GLB_MAGIC: u32 = 0x46_54_6C_67; // "glTF"
+const GLB_VERSION: u32 = 2;
+const GLB_CHUNK_JSON: u32 = 0x4E_4F_53_4A; // "JSON"
+const GLB_CHUNK_BIN: u32 = 0x00_4E_49_42; // "BIN\0"
+
+/// Rotation quaternion taking MagicaVoxel's Z-up axes to glTF's Y-up axes
+const Z_UP_TO_Y_UP: [f32; 4] = [-std::f32::consts::FRAC_1_SQRT_2, 0.0, 0.0, std::f32::consts::FRAC_1_SQRT_2];
+
+/// A mesh with one vertex per distinct position, normal and color combination,
+/// as glTF has no separate index lists per attribute. The color is either a
+/// vertex color or the coordinates of its texel in the palette texture.
+#[derive(Default)]
+struct GltfMesh
+{
+    positions: Vec<Vertex>,
+    normals: Vec<[f32; 3]>,
+    colors: Vec<[f32; 3]>,
+    uvs: Vec<[f32; 2]>,
+    indices: Vec<u32>
+}
+
+fn build_mesh(triangles: &[Triangle], palette: &[u32], texture: Option<TextureLayout>) -> GltfMesh
+{
+    let mut mesh = GltfMesh::default();
+    let mut vertex_indices: HashMap<(LatticeKey, LatticeKey, u8), u32> = HashMap::new();
+
+    for triangle in triangles
+    {
+        let normal = triangle.normal;
+        for vertex in [triangle.a, triangle.b, triangle.c].iter()
+        {
+            let key = (
+                lattice_key(vertex.x, vertex.y, vertex.z),
+                lattice_key(normal.x, normal.y, normal.z),
+                triangle.color_index
+            );
+            let index = *vertex_indices.entry(key).or_insert_with(|| {
+                mesh.positions.push(*vertex);
+                mesh.normals.push([normal.x, normal.y, normal.z]);
+                match texture
+                {
+                    Some(layout) => {
+                        mesh.uvs.push(layout.uv(triangle.color_index));
+                    },
+                    None => {
+                        let linear = Color::from_palette(palette, triangle.color_index).to_linear();
+                        mesh.colors.push([linear[0], linear[1], linear[2]]);
+                    }
+                }
+                (mesh.positions.len() - 1) as u32
+            });
+            mesh.indices.push(index);
+        }
+    }
+
+    return mesh;
+}
+
+/// Collects the binary buffer along with the buffer views and accessors describing it
+#[derive(Default)]
+struct BufferBuilder
+{
+    data: Vec<u8>,
+    buffer_views: Vec<Value>,
+    accessors: Vec<Value>
+}
+
+impl BufferBuilder
+{
+    /// Adds a buffer view of `bytes`. Only vertex data and indices have a `target`.
+    fn push_view(&mut self, bytes: &[u8], target: Option<u32>) -> usize
+    {
+        while !self.data.len().is_multiple_of(4)
+        {
+            self.data.push(0);
+        }
+
+        let mut view = json!({
+            "buffer": 0,
+            "byteOffset": self.data.len(),
+            "byteLength": bytes.len()
+        });
+        if let Some(target) = target
+        {
+            view["target"] = json!(target);
+        }
+        self.buffer_views.push(view);
+        self.data.extend_from_slice(bytes);
+        return self.buffer_views.len() - 1;
+    }
+
+    /// Adds a `VEC3` float accessor, returning its index. Position accessors
+    /// need `bounds` as glTF requires their min and max.
+    fn push_vec3(&mut self, items: &[[f32; 3]], bounds: bool) -> usize
+    {
+        let mut bytes: Vec<u8> = Vec::with_capacity(items.len() * 12);
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+        for item in items
+        {
+            for axis in 0..3
+            {
+                bytes.extend_from_slice(&item[axis].to_le_bytes());
+                min[axis] = min[axis].min(item[axis]);
+                max[axis] = max[axis].max(item[axis]);
+            }
+        }
+
+        let view = self.push_view(&bytes, Some(ARRAY_BUFFER));
+        let mut accessor = json!({
+            "bufferView": view,
+            "componentType": FLOAT,
+            "count": items.len(),
+            "type": "VEC3"
+        });
+        if bounds
+        {
+            accessor["min"] = json!(min);
+            accessor["max"] = json!(max);
+        }
+        self.accessors.push(accessor);
+        return self.accessors.len() - 1;
+    }
+
+    /// Adds a `VEC2` float accessor of texture coordinates, returning its index
+    fn push_vec2(&mut self, items: &[[f32; 2]]) -> usize
+    {
+        let bytes: Vec<u8> = items.iter().flatten().flat_map(|value| value.to_le_bytes().to_vec()).collect();
+        let view = self.push_view(&bytes, Some(ARRAY_BUFFER));
+        self.accessors.push(json!({
+            "bufferView": view,
+            "componentType": FLOAT,
+            "count": items.len(),
+            "type": "VEC2"
+        }));
+        return self.accessors.len() - 1;
+    }
+
+    fn push_indices(&mut self, indices: &[u32]) -> usize
+    {
+        let bytes: Vec<u8> = indices.iter().flat_map(|index| index.to_le_bytes().to_vec()).collect();
+        let view = self.push_view(&bytes, Some(ELEMENT_ARRAY_BUFFER));
+        self.accessors.push(json!({
+            "bufferView": view,
+            "componentType": UNSIGNED_INT,
+            "count": indices.len(),
+            "type": "SCALAR"
+        }));
+        return self.accessors.len() - 1;
+    }
+
+    /// Adds a float accessor of animation data with `components` floats per
+    /// item. Sampler inputs need `bounds` as glTF requires their min and max.
+    fn push_animation_data(&mut self, values: &[f32], kind: &str, components: usize, bounds: bool) -> usize
+    {
+        let bytes: Vec<u8> = values.iter().flat_map(|value| value.to_le_bytes().to_vec()).collect();
+        let view = self.push_view(&bytes, None);
+        let mut accessor = json!({
+            "bufferView": view,
+            "componentType": FLOAT,
+            "count": values.len() / components,
+            "type": kind
+        });
+        if bounds
+        {
+            let mut min = vec![f32::MAX; components];
+            let mut max = vec![f32::MIN; components];
+            for (i, value) in values.iter().enumerate()
+            {
+                min[i % components] = min[i % components].min(*value);
+                max[i % components] = max[i % components].max(*value);
+            }
+            accessor["min"] = json!(min);
+            accessor["max"] = json!(max);
+        }
+        self.accessors.push(accessor);
+        return self.accessors.len() - 1;
+    }
+}
+
+/// Builds the material of a palette index with a `MATL` material, tinted by
+/// the vertex colors or the palette texture like the shared material
+fn build_material(index: u8, color: Color, material: &VoxMaterial, textured: bool) -> Value
+{
+    let mut value = json!({
+        "name": material_name(index),
+        "pbrMetallicRoughness": {
+            "baseColorFactor": [1.0, 1.0, 1.0, material.opacity],
+            "metallicFactor": material.metallic,
+            "roughnessFactor": material.roughness
+        }
+    });
+    if textured
+    {
+        value["pbrMetallicRoughness"]["baseColorTexture"] = json!({
+            "index": 0
+        });
+    }
+    if material.emission > 0.0
+    {
+        // Emission isn't tinted by vertex colors, so it carries the palette color itself
+        let linear = color.to_linear();
+        value["emissiveFactor"] = json!([linear[0] * material.emission, linear[1] * material.emission, linear[2] * material.emission]);
+    }
+    if material.opacity < 1.0
+    {
+        value["alphaMode"] = json!("BLEND");
+    }
+    if material.transmission > 0.0
+    {
+        value["extensions"] = json!({
+            "KHR_materials_transmission": {
+                "transmissionFactor": material.transmission
+            },
+            "KHR_materials_ior": {
+                "ior": material.ior
+            }
+        });
+    }
+    return value;
+}
+
+/// Adds `node` and its children to `nodes`, returning the index of `node`
+fn push_node(nodes: &mut Vec<Value>, node: &SceneNode, meshes: &[Option<usize>], scale: f32) -> usize
+{
+    let index = nodes.len();
+    nodes.push(json!({
+        "name": node.label()
+    }));
+    if node.transform != Transform::IDENTITY
+    {
+        // glTF matrices are column major
+        let m = node.transform.to_scaled_matrix(scale);
+        nodes[index]["matrix"] = json!((0..16).map(|i| m[(i % 4) * 4 + i / 4]).collect::<Vec<f32>>());
+    }
+    match node.model.and_then(|model| meshes[model])
+    {
+        Some(mesh) => {
+            nodes[index]["mesh"] = json!(mesh);
+        },
+        None => {}
+    }
+
+    let children: Vec<usize> = node.children.iter().map(|child| push_node(nodes, child, meshes, scale)).collect();
+    if !children.is_empty()
+    {
+        nodes[index]["children"] = json!(children);
+    }
+    return index;
+}
+
+/// Builds the glTF JSON document and its binary buffer. A single scene is
+/// exported as is, while with an `fps` every scene is a frame of an animation
+/// that shows one frame node after the other. `buffer_uri` is left out for GLB
+/// files, where the buffer and the palette texture are stored in the file itself.
+fn build_document(in_data: &dot_vox::DotVoxData, frames: &[Scene], fps: Option<f32>, options: &MeshOptions, buffer_uri: Option<&str>) -> Result<(Value, Vec<u8>)>
+{
+    if fps.is_some_and(|fps| !(fps.is_finite() && fps > 0.0))
+    {
+        return Err(VoxportError::Export("animation frame rate must be positive".to_string()));
+    }
+
+    let texture = palette_texture(options)?;
+    // glTF positions are in meters
+    let scale = options.voxel_size.map(|voxel_size| voxel_size.meters()).unwrap_or(1.0);
+    let mut buffer = BufferBuilder::default();
+    let mut meshes: Vec<Value> = Vec::new();
+    let vox_materials = palette_materials(in_data);
+    // The first material is shared by every palette index with a plain diffuse material
+    let mut materials: Vec<Value> = vec! {
+        json!({
+            "name": "Voxel",
+            "pbrMetallicRoughness": {
+                "baseColorFactor": [1.0, 1.0, 1.0, 1.0],
+                "metallicFactor": 0.0,
+                "roughnessFactor": 1.0
+            }
+        })
+    };
+    let mut material_indices: BTreeMap<u8, usize> = BTreeMap::new();
+    // Index into `meshes` of every model, models the scenes don't place or
+    // without triangles get no mesh
+    let mut model_meshes: Vec<Option<usize>> = Vec::new();
+    let placed: BTreeSet<usize> = frames.iter().flat_map(|frame| frame.models()).collect();
+
+    for (i, model) in in_data.models.iter().enumerate()
+    {
+        if !placed.contains(&i)
+        {
+            model_meshes.push(None);
+            continue;
+        }
+
+        let triangles = model_pivot(&model.size).apply_triangles(&convert_model(model, options)?);
+        if triangles.is_empty()
+        {
+            model_meshes.push(None);
+            continue;
+        }
+
+        // One primitive per material
+        let mut groups: BTreeMap<usize, Vec<Triangle>> = BTreeMap::new();
+        for triangle in &triangles
+        {
+            let index = triangle.color_index;
+            let material = if vox_materials[index as usize].is_default() { 0 } else {
+                *material_indices.entry(index).or_insert_with(|| {
+                    let color = Color::from_palette(&in_data.palette, index);
+                    materials.push(build_material(index, color, &vox_materials[index as usize], texture.is_some()));
+                    materials.len() - 1
+                })
+            };
+            groups.entry(material).or_default().push(*triangle);
+        }
+
+        let mut primitives: Vec<Value> = Vec::new();
+        for (material, triangles) in &groups
+        {
+            let mut mesh = build_mesh(triangles, &in_data.palette, texture.map(|texture| texture.layout));
+            scale_welded_positions(&mut mesh.positions, scale);
+            let positions: Vec<[f32; 3]> = mesh.positions.iter().map(|vertex| [vertex.x, vertex.y, vertex.z]).collect();
+            let position = buffer.push_vec3(&positions, true);
+            let normal = buffer.push_vec3(&mesh.normals, false);
+            let mut attributes = json!({
+                "POSITION": position,
+                "NORMAL": normal
+            });
+            // Vertex colors would tint the texture, so textured meshes only get coordinates
+            match texture
+            {
+                Some(_) => {
+                    attributes["TEXCOORD_0"] = json!(buffer.push_vec2(&mesh.uvs));
+                },
+                None => {
+                    attributes["COLOR_0"] = json!(buffer.push_vec3(&mesh.colors, false));
+                }
+            }
+            let indices = buffer.push_indices(&mesh.indices);
+            primitives.push(json!({
+                "attributes": attributes,
+                "indices": indices,
+                "material": material
+            }));
+        }
+
+        meshes.push(json!({
+            "name": format!("Voxel-{}-mesh", i),
+            "primitives": primitives
+        }));
+        model_meshes.push(Some(meshes.len() - 1));
+    }
+
+    let mut nodes: Vec<Value> = vec![Value::Null]; // Root node is filled in once its children are known
+    let mut children: Vec<usize> = Vec::new();
+    let mut animations: Vec<Value> = Vec::new();
+    match fps
+    {
+        Some(fps) => {
+            // glTF can't animate visibility, so hidden frames are scaled down to nothing
+            let count = frames.len();
+            let times: Vec<f32> = (0..=count).map(|key| key as f32 / fps).collect();
+            let input = buffer.push_animation_data(&times, "SCALAR", 1, true);
+            let mut samplers: Vec<Value> = Vec::new();
+            let mut channels: Vec<Value> = Vec::new();
+
+            for (i, frame) in frames.iter().enumerate()
+            {
+                let frame_node = nodes.len();
+                nodes.push(json!({
+                    "name": format!("Frame-{}", i)
+                }));
+                if i != 0
+                {
+                    nodes[frame_node]["scale"] = json!([0.0, 0.0, 0.0]);
+                }
+                let root = push_node(&mut nodes, &frame.root, &model_meshes, scale);
+                nodes[frame_node]["children"] = json!([root]);
+                children.push(frame_node);
+
+                // The extra key at the end holds the last frame for its duration
+                let scales: Vec<f32> = (0..=count)
+                    .flat_map(|key| {
+                        let shown = if key.min(count - 1) == i { 1.0 } else { 0.0 };
+                        vec![shown; 3]
+                    })
+                    .collect();
+                let output = buffer.push_animation_data(&scales, "VEC3", 3, false);
+                samplers.push(json!({
+                    "input": input,
+                    "output": output,
+                    "interpolation": "STEP"
+                }));
+                channels.push(json!({
+                    "sampler": samplers.len() - 1,
+                    "target": {
+                        "node": frame_node,
+                        "path": "scale"
+                    }
+                }));
+            }
+
+            if !channels.is_empty()
+            {
+                animations.push(json!({
+                    "name": "Frames",
+                    "samplers": samplers,
+                    "channels": channels
+                }));
+            }
+        },
+        None => {
+            for frame in frames
+            {
+                children.push(push_node(&mut nodes, &frame.root, &model_meshes, scale));
+            }
+        }
+    }
+
+    nodes[0] = json!({
+        "name": "Scene",
+        "rotation": Z_UP_TO_Y_UP
+    });
+
+    let mut image: Option<Value> = None;
+    match texture
+    {
+        Some(texture) if !meshes.is_empty() => {
+            match buffer_uri
+            {
+                Some(_) => {
+                    image = Some(json!({
+                        "uri": texture.uri
+                    }));
+                },
+                None => {
+                    let mut png: Vec<u8> = Vec::new();
+                    export_palette_texture(in_data, texture.layout, &mut png)?;
+                    image = Some(json!({
+                        "bufferView": buffer.push_view(&png, None),
+                        "mimeType": "image/png"
+                    }));
+                }
+            }
+        },
+        _ => {}
+    }
+
+    let mut gltf_buffer = json!({
+        "byteLength": buffer.data.len()
+    });
+    if let Some(uri) = buffer_uri
+    {
+        gltf_buffer["uri"] = json!(uri);
+    }
+
+    let mut document = json!({
+        "asset": {
+            "version": "2.0",
+            "generator": "Voxport"
+        },
+        "scene": 0,
+        "scenes": [{
+            "name": "Scene",
+            "nodes": [0]
+        }],
+        "nodes": nodes
+    });
+
+    let extensions: BTreeSet<String> = materials.iter()
+        .filter_map(|material| material["extensions"].as_object())
+        .flat_map(|extensions| extensions.keys().cloned())
+        .collect();
+
+    // glTF forbids empty arrays, so they are only added when something goes in them
+    if !children.is_empty()
+    {
+        document["nodes"][0]["children"] = json!(children);
+    }
+    if !animations.is_empty()
+    {
+        document["animations"] = json!(animations);
+    }
+    if !meshes.is_empty()
+    {
+        document["meshes"] = json!(meshes);
+    }
+    if let Some(image) = image
+    {
+        // Nearest filtering keeps neighbouring palette entries from bleeding in
+        materials[0]["pbrMetallicRoughness"]["baseColorTexture"] = json!({
+            "index": 0
+        });
+        document["samplers"] = json!([{
+            "magFilter": NEAREST,
+            "minFilter": NEAREST
+        }]);
+        document["textures"] = json!([{
+            "sampler": 0,
+            "source": 0
+        }]);
+        document["images"] = json!([image]);
+    }
+    document["materials"] = json!(materials);
+    if !extensions.is_empty()
+    {
+        document["extensionsUsed"] = json!(extensions);
+    }
+    if !buffer.data.is_empty()
+    {
+        document["buffers"] = json!([gltf_buffer]);
+        document["bufferViews"] = json!(buffer.buffer_views);
+        document["accessors"] = json!(buffer.accessors);
+    }
+
+    return Ok((document, buffer.data));
+}
+
+fn write_gltf<W: Write, B: Write>(document: &Value, data: &[u8], writer: &mut W, bin_writer: &mut B) -> Result<()>
+{
+    serde_json::to_writer_pretty(&mut *writer, document)?;
+    writer.write_all(b"\n")?;
+    bin_writer.write_all(data)?;
+    return Ok(());
+}
+
+fn write_glb<W: Write>(document: &Value, mut data: Vec<u8>, writer: &mut W) -> Result<()>
+{
+    let mut json_data = serde_json::to_vec(document)?;
+    while !json_data.len().is_multiple_of(4)
+    {
+        json_data.push(b' ');
+    }
+    while !data.len().is_multiple_of(4)
+    {
+        data.push(0);
+    }
+
+    let mut length = 12 + 8 + json_data.len();
+    if !data.is_empty()
+    {
+        length += 8 + data.len();
+    }
+
+    writer.write_all(&GLB_MAGIC.to_le_bytes())?;
+    writer.write_all(&GLB_VERSION.to_le_bytes())?;
+    writer.write_all(&(length as u32).to_le_bytes())?;
+
+    writer.write_all(&(json_data.len() as u32).to_le_bytes())?;
+    writer.write_all(&GLB_CHUNK_JSON.to_le_bytes())?;
+    writer.write_all(&json_data)?;
+
+    if !data.is_empty()
+    {
+        writer.write_all(&(data.len() as u32).to_le_bytes())?;
+        writer.write_all(&GLB_CHUNK_BIN.to_le_bytes())?;
+        writer.write_all(&data)?;
+    }
+
+    return Ok(());
+}
+
+/// Meshes the models placed by `scene` and writes a `.gltf` document with a node
+/// for every transform node of `scene` to `writer`, with its binary buffer
+/// written to `bin_writer`. `bin_uri` is the path of the buffer relative to the
+/// document. A palette texture is only referenced, see `export_palette_texture`.
+pub fn convert_vox_gltf<W: Write, B: Write>(in_data: &dot_vox::DotVoxData, scene: &Scene, options: &MeshOptions, writer: &mut W, bin_writer: &mut B, bin_uri: &str) -> Result<()>
+{
+    let (document, data) = build_document(in_data, std::slice::from_ref(scene), None, options, Some(bin_uri))?;
+    return write_gltf(&document, &data, writer, bin_writer);
+}
+
+/// Meshes the models placed by `scene` and writes them as a single binary `.glb` file
+pub fn convert_vox_glb<W: Write>(in_data: &dot_vox::DotVoxData, scene: &Scene, options: &MeshOptions, writer: &mut W) -> Result<()>
+{
+    let (document, data) = build_document(in_data, std::slice::from_ref(scene), None, options, None)?;
+    return write_glb(&document, data, writer);
+}
+
+/// Writes `frames` as a `.gltf` animation playing at `fps` frames per second,
+/// like `convert_vox_gltf` does for a single scene
+pub fn convert_animation_gltf<W: Write, B: Write>(in_data: &dot_vox::DotVoxData, frames: &[Scene], fps: f32, options: &MeshOptions, writer: &mut W, bin_writer: &mut B, bin_uri: &str) -> Result<()>
+{
+    let (document, data) = build_document(in_data, frames, Some(fps), options, Some(bin_uri))?;
+    return write_gltf(&document, &data, writer, bin_writer);
+}
+
+/// Writes `frames` as a `.glb` animation playing at `fps` frames per second
+pub fn convert_animation_glb<W: Write>(in_data: &dot_vox::DotVoxData, frames: &[Scene], fps: f32, options: &MeshOptions, writer: &mut W) -> Result<()>
+{
+    let (document, data) = build_document(in_data, frames, Some(fps), options, None)?;
+    return write_glb(&document, data, writer);
+}
@@ -0,0 +1,133 @@
+//! Greedy meshing: merges runs of coplanar exposed faces into maximal rectangles
+//! instead of emitting two triangles for every voxel face.
+
+use crate::mesh::{get_voxel_idx, push_face, Face, MetaVoxel, Triangle, Vertex, FACES};
+
+/// Returns which voxel axis `face` points along, followed by the two axes spanning its plane
+fn face_axes(face: Face) -> (usize, usize, usize)
+{
+    match face
+    {
+        Face::Left | Face::Right => (0, 1, 2),
+        Face::Back | Face::Front => (1, 0, 2),
+        Face::Bottom | Face::Top => (2, 0, 1)
+    }
+}
+
+fn to_vertex(pos: [usize; 3]) -> Vertex
+{
+    return Vertex {
+        x: pos[0] as f32,
+        y: pos[1] as f32,
+        z: pos[2] as f32
+    };
+}
+
+/// Emits triangles for every exposed face in `mvoxels`, merging adjacent coplanar
+/// faces into rectangles. Faces with different palette indices are only merged
+/// when `merge_colors` is set, in which case a merged face takes the palette
+/// index of its first voxel.
+pub fn convert_greedy_triangles(mvoxels: &[Option<MetaVoxel>], size: &dot_vox::Size, merge_colors: bool) -> Vec<Triangle>
+{
+    let mut triangles = Vec::new();
+    let dims = [size.x as usize, size.y as usize, size.z as usize];
+    let can_merge = |cell: Option<u8>, color_index: u8| {
+        match cell
+        {
+            Some(cell_index) => merge_colors || cell_index == color_index,
+            None => false
+        }
+    };
+
+    for face in FACES.iter()
+    {
+        let (d, u, v) = face_axes(*face);
+        let (width, height) = (dims[u], dims[v]);
+        // Palette index of the exposed face in each cell of the current slice
+        let mut mask: Vec<Option<u8>> = vec![None; width * height];
+
+        for slice in 0..dims[d]
+        {
+            for cv in 0..height
+            {
+                for cu in 0..width
+                {
+                    let mut pos = [0usize; 3];
+                    pos[d] = slice;
+                    pos[u] = cu;
+                    pos[v] = cv;
+                    let voxel = dot_vox::Voxel {
+                        x: pos[0] as u8,
+                        y: pos[1] as u8,
+                        z: pos[2] as u8,
+                        i: 0
+                    };
+
+                    mask[cu + cv * width] = match mvoxels[get_voxel_idx(&voxel, size)]
+                    {
+                        Some(mvoxel) if mvoxel.has_face(*face) => Some(mvoxel.voxel.i),
+                        _ => None
+                    };
+                }
+            }
+
+            for cv in 0..height
+            {
+                let mut cu = 0;
+                while cu < width
+                {
+                    let color_index = match mask[cu + cv * width]
+                    {
+                        Some(color_index) => color_index,
+                        None => {
+                            cu += 1;
+                            continue;
+                        }
+                    };
+
+                    let mut quad_width = 1;
+                    while cu + quad_width < width && can_merge(mask[cu + quad_width + cv * width], color_index)
+                    {
+                        quad_width += 1;
+                    }
+
+                    let mut quad_height = 1;
+                    'grow: while cv + quad_height < height
+                    {
+                        for ku in cu..(cu + quad_width)
+                        {
+                            if !can_merge(mask[ku + (cv + quad_height) * width], color_index)
+                            {
+                                break 'grow;
+                            }
+                        }
+                        quad_height += 1;
+                    }
+
+                    for kv in cv..(cv + quad_height)
+                    {
+                        for ku in cu..(cu + quad_width)
+                        {
+                            mask[ku + kv * width] = None;
+                        }
+                    }
+
+                    // The quad is a face of the box covering the merged voxels
+                    let mut lo = [0usize; 3];
+                    let mut hi = [0usize; 3];
+                    lo[d] = slice;
+                    hi[d] = slice + 1;
+                    lo[u] = cu;
+                    hi[u] = cu + quad_width;
+                    lo[v] = cv;
+                    hi[v] = cv + quad_height;
+                    push_face(&mut triangles, *face, to_vertex(lo), to_vertex(hi), color_index);
+
+                    cu += quad_width;
+                }
+            }
+        }
+    }
+
+    return triangles;
+}
@@ -0,0 +1,189 @@
+//! Hollowing of models for 3D printing: removes the voxels deeper inside than a
+//! given wall thickness and drills drain holes letting resin out of the cavity.
+
+use std::collections::VecDeque;
+
+use crate::mesh::{get_voxel_idx, outside_cells, voxel_at};
+
+/// Where a drain hole is drilled, straight down from the cavity through the bottom wall
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Drain
+{
+    /// Below the lowest cell of the cavity in the column at this x and y
+    Column(u8, u8),
+    /// Below the lowest cell of every separate cavity
+    Lowest
+}
+
+/// How models are hollowed
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hollow
+{
+    /// Voxels further than this from the outside, counting diagonal steps as
+    /// one, are removed. Walls are at least this many voxels thick.
+    pub wall: u32,
+    pub drains: Vec<Drain>
+}
+
+/// Steps to the 26 cells around a cell
+fn neighborhood() -> Vec<[i64; 3]>
+{
+    let mut steps: Vec<[i64; 3]> = Vec::with_capacity(26);
+    for z in -1..=1
+    {
+        for y in -1..=1
+        {
+            for x in -1..=1
+            {
+                if [x, y, z] != [0, 0, 0]
+                {
+                    steps.push([x, y, z]);
+                }
+            }
+        }
+    }
+    return steps;
+}
+
+/// Removes the cells below `idx` in its column down to the first one that is
+/// `outside`, so only the wall under the cavity is cut
+fn drill_down(voxels: &mut [Option<dot_vox::Voxel>], size: &dot_vox::Size, outside: &[bool], idx: usize)
+{
+    let layer = (size.x * size.y) as usize;
+    let mut below = idx;
+    while below >= layer
+    {
+        below -= layer;
+        if outside[below]
+        {
+            return;
+        }
+        voxels[below] = None;
+    }
+}
+
+/// Hollows the voxel grid of a model as returned by `reorder_voxels`. Sealed
+/// cavities already in the model join the one left by hollowing. Drain columns
+/// that don't cross the cavity are left alone.
+pub fn hollow_voxels(voxels: &mut [Option<dot_vox::Voxel>], size: &dot_vox::Size, hollow: &Hollow)
+{
+    let dims = [size.x as i64, size.y as i64, size.z as i64];
+    let outside = outside_cells(voxels, size);
+    let steps = neighborhood();
+    let cell = |pos: [i64; 3]| -> Option<usize> {
+        if (0..3).any(|axis| pos[axis] < 0 || pos[axis] >= dims[axis])
+        {
+            return None;
+        }
+        let voxel = dot_vox::Voxel { x: pos[0] as u8, y: pos[1] as u8, z: pos[2] as u8, i: 0 };
+        return Some(get_voxel_idx(&voxel, size));
+    };
+    let position = |idx: usize| -> [i64; 3] {
+        let voxel = voxel_at(idx, size, 0);
+        return [voxel.x as i64, voxel.y as i64, voxel.z as i64];
+    };
+
+    // Distance of every voxel from the outside, starting with the voxels next to it
+    let mut depth: Vec<u32> = vec![0; voxels.len()];
+    let mut queue: VecDeque<usize> = VecDeque::new();
+    for idx in 0..voxels.len()
+    {
+        let pos = position(idx);
+        let touches_outside = steps.iter().any(|step| {
+            match cell([pos[0] + step[0], pos[1] + step[1], pos[2] + step[2]])
+            {
+                Some(neighbor) => outside[neighbor],
+                None => true
+            }
+        });
+        if voxels[idx].is_some() && touches_outside
+        {
+            depth[idx] = 1;
+            queue.push_back(idx);
+        }
+    }
+    while let Some(idx) = queue.pop_front()
+    {
+        let pos = position(idx);
+        for step in &steps
+        {
+            match cell([pos[0] + step[0], pos[1] + step[1], pos[2] + step[2]])
+            {
+                Some(neighbor) if voxels[neighbor].is_some() && depth[neighbor] == 0 => {
+                    depth[neighbor] = depth[idx] + 1;
+                    queue.push_back(neighbor);
+                },
+                _ => {}
+            }
+        }
+    }
+
+    // Every cell that isn't wall or outside is cavity from here on
+    let mut cavity: Vec<bool> = vec![false; voxels.len()];
+    for idx in 0..voxels.len()
+    {
+        if voxels[idx].is_some() && depth[idx] > hollow.wall
+        {
+            voxels[idx] = None;
+        }
+        cavity[idx] = voxels[idx].is_none() && !outside[idx];
+    }
+
+    let mut drills: Vec<usize> = Vec::new();
+    for drain in &hollow.drains
+    {
+        match drain
+        {
+            Drain::Column(x, y) => {
+                let mut column = (0..dims[2]).filter_map(|z| cell([*x as i64, *y as i64, z]));
+                match column.find(|idx| cavity[*idx])
+                {
+                    Some(idx) => {
+                        drills.push(idx);
+                    },
+                    None => {}
+                }
+            },
+            Drain::Lowest => {
+                // Cells come up layer by layer, so the first one of a cavity is its lowest
+                let mut seen: Vec<bool> = vec![false; voxels.len()];
+                for idx in 0..voxels.len()
+                {
+                    if !cavity[idx] || seen[idx]
+                    {
+                        continue;
+                    }
+                    drills.push(idx);
+
+                    seen[idx] = true;
+                    let mut stack: Vec<usize> = vec![idx];
+                    while let Some(current) = stack.pop()
+                    {
+                        let pos = position(current);
+                        for axis in 0..3
+                        {
+                            for offset in [-1, 1].iter()
+                            {
+                                let mut next = pos;
+                                next[axis] += offset;
+                                match cell(next)
+                                {
+                                    Some(neighbor) if cavity[neighbor] && !seen[neighbor] => {
+                                        seen[neighbor] = true;
+                                        stack.push(neighbor);
+                                    },
+                                    _ => {}
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    for idx in drills
+    {
+        drill_down(voxels, size, &outside, idx);
+    }
+}
@@ -0,0 +1,79 @@
+//! Library for converting MagicaVoxel `.vox` files into common mesh formats.
+//!
+//! The conversion pipeline works on an in-memory `dot_vox::DotVoxData`, so it
+//! can be driven from build tools without going through the `voxport` binary.
+#![allow(clippy::needless_return, clippy::from_over_into, clippy::identity_op, clippy::single_match, clippy::needless_late_init)]
+
+extern crate collada_io; // Export
+extern crate dot_vox; // Import
+extern crate png; // Export
+extern crate serde_json; // Export
+extern crate stl_io; // Export
+extern crate xml; // Export
+
+pub mod color;
+pub mod dae;
+pub mod error;
+pub mod gltf;
+pub mod greedy;
+pub mod hollow;
+pub mod manifold;
+pub mod material;
+pub mod mesh;
+pub mod obj;
+pub mod palette;
+pub mod ply;
+pub mod scene;
+pub mod stl;
+pub mod units;
+pub mod weld;
+
+pub use color::Color;
+pub use dae::{convert_animation_dae, convert_vox_dae};
+pub use error::{Result, VoxportError};
+pub use gltf::{convert_animation_glb, convert_animation_gltf, convert_vox_glb, convert_vox_gltf};
+pub use hollow::{Drain, Hollow};
+pub use material::{palette_materials, VoxMaterial};
+pub use mesh::{convert_instances, convert_model, convert_vox, split_by_color, ColorGroup, ColorPart, Face, IndexedTriangle, MeshOptions, Mesher, MetaVoxel, Normal, Triangle, Vertex};
+pub use obj::convert_vox_obj;
+pub use palette::{apply_palette, export_jasc_palette, export_palette, export_palette_texture, import_palette, remap_palette_indices, used_palette_indices, PaletteFormat, PaletteTexture, TextureLayout};
+pub use ply::{convert_vox_ply, PlyFormat};
+pub use scene::{Instance, Layer, Pivot, Scene, SceneNode, ScenePart, Selection, SplitMode, Transform};
+pub use stl::{convert_vox_stl, StlColor, StlFormat};
+pub use units::{Unit, VoxelSize};
+
+/// Reads and parses a MagicaVoxel file from disk
+pub fn load(path: &str) -> Result<dot_vox::DotVoxData>
+{
+    let bytes = std::fs::read(path).map_err(VoxportError::NoInput)?;
+    return load_bytes(&bytes);
+}
+
+/// Parses an in-memory MagicaVoxel file
+pub fn load_bytes(bytes: &[u8]) -> Result<dot_vox::DotVoxData>
+{
+    // dot_vox indexes into the buffer without checking its length first, so
+    // the header and the chunk sizes are checked against it beforehand
+    if bytes.len() < 8 || &bytes[0..4] != b"VOX "
+    {
+        return Err(VoxportError::Parse("missing VOX header".to_string()));
+    }
+    scene::read_chunks(bytes)?;
+
+    return dot_vox::load_bytes(bytes).map_err(|msg| VoxportError::Parse(msg.to_string()));
+}
+
+/// Reads a MagicaVoxel file from disk along with its scene graph
+pub fn load_scene(path: &str) -> Result<(dot_vox::DotVoxData, Scene)>
+{
+    let bytes = std::fs::read(path).map_err(VoxportError::NoInput)?;
+    return load_scene_bytes(&bytes);
+}
+
+/// Parses an in-memory MagicaVoxel file along with its scene graph
+pub fn load_scene_bytes(bytes: &[u8]) -> Result<(dot_vox::DotVoxData, Scene)>
+{
+    let in_data = load_bytes(bytes)?;
+    let scene = Scene::read(bytes, &in_data)?;
+    return Ok((in_data, scene));
+}
@@ -1,707 +1,367 @@
-extern crate collada_io; // Export
+#![allow(clippy::needless_return, clippy::redundant_static_lifetimes, clippy::needless_late_init)]
+
 extern crate clap; // CLI
-extern crate dot_vox; // Import
-extern crate stl_io; // Export
+extern crate dot_vox;
+extern crate voxport;
 
 pub mod app;
 
 const INPUT_FILEPATH: &'static str = "input.vox";
 const OUTPUT_STL_FILEPATH: &'static str = "output.stl";
 const OUTPUT_DAE_FILEPATH: &'static str = "output.dae";
-const _OUTPUT_PAL_FILEPATH: &'static str = "output.txt";
+const OUTPUT_GLTF_FILEPATH: &'static str = "output.gltf";
+const OUTPUT_OBJ_FILEPATH: &'static str = "output.obj";
+const OUTPUT_PLY_FILEPATH: &'static str = "output.ply";
+const OUTPUT_PAL_FILEPATH: &'static str = "output.pal";
 
 use std::fs::File;
-use std::io::prelude::*;
-use std::io::LineWriter;
-
-struct Color
-{
-    pub r: u8,
-    pub g: u8,
-    pub b: u8,
-    pub a: u8
-}
+use std::path::Path;
+use std::process;
 
-#[derive(Copy, Clone)]
-struct MetaVoxel
-{
-    pub voxel: dot_vox::Voxel,
-    pub faces: u8
-}
+use voxport::VoxportError;
 
-#[derive(Copy, Clone, PartialEq)]
-struct Vertex
+/// Prints `err` along with the file it concerns and exits with its exit code
+fn exit_with_error(path: &str, err: VoxportError) -> !
 {
-    pub x: f32,
-    pub y: f32,
-    pub z: f32
+    eprintln!("voxport: {}: {}", path, err);
+    process::exit(err.exit_code());
 }
 
-#[derive(Copy, Clone, PartialEq)]
-struct Normal
+/// Reports a usage error the argument parser cannot express, the way it
+/// reports its own
+fn usage_error(message: &str) -> !
 {
-    pub x: f32,
-    pub y: f32,
-    pub z: f32
+    eprintln!("error: {}\n\nFor more information try --help", message);
+    process::exit(2);
 }
 
-#[derive(Copy, Clone, PartialEq)]
-struct Triangle
+fn create_output(path: &str) -> File
 {
-    pub normal: Normal,
-    pub a: Vertex,
-    pub b: Vertex,
-    pub c: Vertex
+    return File::create(path).unwrap_or_else(|err| exit_with_error(path, err.into()));
 }
 
-#[derive(Copy, Clone, PartialEq)]
-struct IndexedTriangle
+fn mesh_options(matches: &clap::ArgMatches) -> voxport::MeshOptions
 {
-    pub normal: Normal, // STL
-    pub normal_index: usize, // DAE
-    pub a: usize,
-    pub b: usize,
-    pub c: usize
-}
+    let greedy = voxport::Mesher::Greedy {
+        merge_colors: matches.is_present("merge-colors")
+    };
+    let mesher = match matches.value_of("mesher")
+    {
+        Some("naive") => voxport::Mesher::Naive,
+        Some("manifold") => voxport::Mesher::Manifold,
+        // Color parts are printed together, so each has to be a closed body
+        Some(_) if matches.is_present("split-colors") => {
+            usage_error("--split-colors needs a closed surface, from --mesher naive or manifold")
+        },
+        Some(_) => greedy,
+        // Greedy quads leave T-junctions, so STL files for printing get every voxel face
+        None if matches.is_present("stl") && !matches.is_present("merge-colors") => voxport::Mesher::Naive,
+        None => greedy
+    };
 
-impl Into<stl_io::Normal> for Normal
-{
-    fn into(self) -> stl_io::Normal
-    { 
-        stl_io::Normal::new([self.x, self.y, self.z])
-    }
+    return voxport::MeshOptions {
+        mesher,
+        texture: None,
+        fill_cavities: matches.is_present("fill-cavities"),
+        // Values already checked by the argument validators
+        hollow: matches.value_of("hollow").and_then(|wall| app::parse_wall(wall).ok()).map(|wall| voxport::Hollow {
+            wall,
+            drains: matches.values_of("drain")
+                .map(|values| values.filter_map(|value| app::parse_drain(value).ok()).collect())
+                .unwrap_or_default()
+        }),
+        voxel_size: matches.value_of("voxel-size").and_then(|size| app::parse_voxel_size(size).ok()),
+        pivot: matches.value_of("pivot").and_then(|pivot| app::parse_pivot(pivot).ok()).unwrap_or_default()
+    };
 }
 
-impl Into<stl_io::Vertex> for Vertex
+fn is_glb(out_file: &str) -> bool
 {
-    fn into(self) -> stl_io::Vertex
-    { 
-        stl_io::Vertex::new([self.x, self.y, self.z])
-    }
+    return Path::new(out_file).extension().is_some_and(|ext| ext.eq_ignore_ascii_case("glb"));
 }
 
-impl Into<stl_io::Triangle> for Triangle
+/// Writes the palette texture next to `out_file` when faces are mapped onto
+/// one, returning `options` pointing at it. GLB files hold the texture themselves.
+fn texture_options(matches: &clap::ArgMatches, in_data: &dot_vox::DotVoxData, options: &voxport::MeshOptions, out_file: &str) -> voxport::MeshOptions
 {
-    fn into(self) -> stl_io::Triangle
-    { 
-        stl_io::Triangle {
-            normal: self.normal.into(),
-            vertices: [self.a.into(), self.b.into(), self.c.into()]
+    let layout = match matches.value_of("texture")
+    {
+        Some("strip") => voxport::TextureLayout::Strip,
+        Some("grid") => voxport::TextureLayout::Grid,
+        _ => {
+            return options.clone();
         }
-    }
-}
+    };
 
-impl Into<stl_io::IndexedTriangle> for IndexedTriangle
-{
-    fn into(self) -> stl_io::IndexedTriangle
-    { 
-        stl_io::IndexedTriangle {
-            normal: self.normal.into(),
-            vertices: [self.a, self.b, self.c]
-        }
+    let out_path = Path::new(out_file);
+    let texture_path = out_path.with_file_name(format!("{}_palette.png", out_path.file_stem().unwrap_or_default().to_string_lossy()));
+    let texture_uri = texture_path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+    if !is_glb(out_file)
+    {
+        let texture_file = texture_path.to_string_lossy();
+        let mut texture = create_output(&texture_file);
+        voxport::export_palette_texture(in_data, layout, &mut texture).unwrap_or_else(|err| exit_with_error(&texture_file, err));
     }
+
+    return voxport::MeshOptions {
+        texture: Some(voxport::PaletteTexture {
+            layout,
+            uri: texture_uri
+        }),
+        ..options.clone()
+    };
 }
 
-impl From<u32> for Color
+/// Output file used when none is given, by export format
+fn default_output(matches: &clap::ArgMatches) -> &'static str
 {
-    fn from(src: u32) -> Self 
-    { 
-        let r = ((src & 0x00_00_00_FF) >> 0) as u8;
-        let g = ((src & 0x00_00_FF_00) >> 8) as u8;
-        let b = ((src & 0x00_FF_00_00) >> 16) as u8;
-        let a = ((src & 0xFF_00_00_00) >> 24) as u8;
-        Self {
-            r,
-            g,
-            b,
-            a
-        } 
+    if matches.is_present("stl")
+    {
+        return OUTPUT_STL_FILEPATH;
+    } else if matches.is_present("dae")
+    {
+        return OUTPUT_DAE_FILEPATH;
+    } else if matches.is_present("gltf")
+    {
+        return OUTPUT_GLTF_FILEPATH;
+    } else if matches.is_present("obj")
+    {
+        return OUTPUT_OBJ_FILEPATH;
     }
+    return OUTPUT_PLY_FILEPATH;
 }
 
-impl Into<String> for Color
+/// Fills in the file name template of the split part `index` named `name`,
+/// placing the file next to `out_file`
+fn part_path(template: &str, out_file: &str, index: usize, name: &str) -> String
 {
-    fn into(self) -> String
-    {
-        format!("{} {} {}", self.r, self.g, self.b)
-    }
+    let out_path = Path::new(out_file);
+    let stem = out_path.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = out_path.extension().unwrap_or_default().to_string_lossy();
+    // Names come from the input file, so they must not reach into other directories
+    let name: String = name.chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' || c == '.' { c } else { '_' })
+        .collect();
+
+    let file_name = template.replace("{stem}", &stem)
+        .replace("{ext}", &ext)
+        .replace("{index}", &index.to_string())
+        .replace("{name}", &name);
+    return out_path.with_file_name(file_name).to_string_lossy().into_owned();
 }
 
-impl MetaVoxel
+/// Writes the models placed by `scene` to `out_file` in the chosen export format
+fn export(matches: &clap::ArgMatches, in_data: &dot_vox::DotVoxData, scene: &voxport::Scene, options: &voxport::MeshOptions, out_file: &str)
 {
-    pub fn has_left(&self) -> bool
-    {
-        return ((self.faces & (1 << 0)) >> 0) == 1;
-    }
-    
-    pub fn has_back(&self) -> bool
+    let options = &texture_options(matches, in_data, options, out_file);
+    let mut file = create_output(out_file);
+    if matches.is_present("stl")
     {
-        return ((self.faces & (1 << 1)) >> 1) == 1;
-    }
-
-    pub fn has_bottom(&self) -> bool
+        let format = match matches.value_of("stl-color")
+        {
+            Some("viscam") => voxport::StlFormat::ColorBinary(voxport::StlColor::VisCam),
+            Some("materialise") => voxport::StlFormat::ColorBinary(voxport::StlColor::Materialise),
+            _ if matches.is_present("ascii") => voxport::StlFormat::Ascii,
+            _ => voxport::StlFormat::Binary
+        };
+        voxport::convert_vox_stl(in_data, scene, options, format, &mut file).unwrap_or_else(|err| exit_with_error(out_file, err));
+    } else if matches.is_present("dae")
     {
-        return ((self.faces & (1 << 2)) >> 2) == 1;
-    }
-
-    pub fn has_right(&self) -> bool
+        voxport::convert_vox_dae(in_data, scene, options, &mut file).unwrap_or_else(|err| exit_with_error(out_file, err));
+    } else if matches.is_present("gltf")
     {
-        return ((self.faces & (1 << 3)) >> 3) == 1;
-    }
-    
-    pub fn has_front(&self) -> bool
+        let out_path = Path::new(out_file);
+        if is_glb(out_file)
+        {
+            voxport::convert_vox_glb(in_data, scene, options, &mut file).unwrap_or_else(|err| exit_with_error(out_file, err));
+        } else {
+            let bin_path = out_path.with_extension("bin");
+            let bin_file = bin_path.to_string_lossy();
+            let bin_uri = bin_path.file_name().unwrap_or_default().to_string_lossy();
+            let mut bin = create_output(&bin_file);
+            voxport::convert_vox_gltf(in_data, scene, options, &mut file, &mut bin, &bin_uri).unwrap_or_else(|err| exit_with_error(out_file, err));
+        }
+    } else if matches.is_present("obj")
     {
-        return ((self.faces & (1 << 4)) >> 4) == 1;
-    }
-
-    pub fn has_top(&self) -> bool
+        let mtl_path = Path::new(out_file).with_extension("mtl");
+        let mtl_file = mtl_path.to_string_lossy();
+        let mtl_uri = mtl_path.file_name().unwrap_or_default().to_string_lossy();
+        let mut mtl = create_output(&mtl_file);
+        voxport::convert_vox_obj(in_data, scene, options, &mut file, &mut mtl, &mtl_uri).unwrap_or_else(|err| exit_with_error(out_file, err));
+    } else if matches.is_present("ply")
     {
-        return ((self.faces & (1 << 5)) >> 5) == 1;
+        let format = if matches.is_present("ascii") { voxport::PlyFormat::Ascii } else { voxport::PlyFormat::BinaryLittleEndian };
+        voxport::convert_vox_ply(in_data, scene, options, format, &mut file).unwrap_or_else(|err| exit_with_error(out_file, err));
     }
 }
 
-fn get_voxel_idx(vox: &dot_vox::Voxel, size: &dot_vox::Size) -> usize
+/// Writes `frames` to `out_file` as an animation in the chosen export format,
+/// which the argument parser only lets be DAE or glTF
+fn export_animation(matches: &clap::ArgMatches, in_data: &dot_vox::DotVoxData, frames: &[voxport::Scene], options: &voxport::MeshOptions, out_file: &str)
 {
-    return (vox.x as usize) + 
-           ((vox.y as usize) * (size.x as usize)) + 
-           ((vox.z as usize) * (size.x as usize) * (size.y as usize));
-}
-
-// Turn the Vec
-fn reorder_voxels(voxels: &mut Vec<dot_vox::Voxel>, size: &dot_vox::Size) -> Vec<Option<dot_vox::Voxel>>
-{
-    let mut ret_voxels: Vec<Option<dot_vox::Voxel>> = Vec::new();
-    let len: usize = (size.x as usize) * (size.y as usize) * (size.z as usize);
-    ret_voxels.resize(len, None);
-    for voxel in voxels
+    // Always given through its default value, and already checked by the argument validator
+    let fps = app::parse_fps(matches.value_of("fps").unwrap_or_default())
+        .unwrap_or_else(|err| clap::Error::with_description(err, clap::ErrorKind::ValueValidation).exit());
+    let options = &texture_options(matches, in_data, options, out_file);
+    let mut file = create_output(out_file);
+    if matches.is_present("dae")
     {
-        let idx = get_voxel_idx(voxel, size);
-        ret_voxels[idx] = Some(*voxel);
+        voxport::convert_animation_dae(in_data, frames, fps, options, &mut file).unwrap_or_else(|err| exit_with_error(out_file, err));
+    } else if is_glb(out_file)
+    {
+        voxport::convert_animation_glb(in_data, frames, fps, options, &mut file).unwrap_or_else(|err| exit_with_error(out_file, err));
+    } else {
+        let bin_path = Path::new(out_file).with_extension("bin");
+        let bin_file = bin_path.to_string_lossy();
+        let bin_uri = bin_path.file_name().unwrap_or_default().to_string_lossy();
+        let mut bin = create_output(&bin_file);
+        voxport::convert_animation_gltf(in_data, frames, fps, options, &mut file, &mut bin, &bin_uri).unwrap_or_else(|err| exit_with_error(out_file, err));
     }
-
-    return ret_voxels;
 }
 
-fn has_neighbor(voxels: &Vec<Option<dot_vox::Voxel>>, voxel: &dot_vox::Voxel, size: &dot_vox:: Size, x: i16, y: i16, z: i16) -> Result<bool, std::num::TryFromIntError>
+/// Runs the `palette` subcommand
+fn export_palette(matches: &clap::ArgMatches)
 {
-    use std::convert::TryFrom;
-    let vx: i16 = voxel.x.into();
-    let vy: i16 = voxel.y.into();
-    let vz: i16 = voxel.z.into();
-
-    let pos = dot_vox::Voxel {
-        i: voxel.i,
-        x: u8::try_from(vx + x)?,
-        y: u8::try_from(vy + y)?,
-        z: u8::try_from(vz + z)?
+    let in_file = matches.value_of("input").unwrap_or(INPUT_FILEPATH);
+    let out_file = matches.value_of("output").unwrap_or(OUTPUT_PAL_FILEPATH);
+    let extension = Path::new(out_file).extension().unwrap_or_default().to_string_lossy().to_lowercase();
+    let format = match matches.value_of("format").unwrap_or(&extension)
+    {
+        "gpl" => voxport::PaletteFormat::Gimp,
+        "act" => voxport::PaletteFormat::Act,
+        "hex" => voxport::PaletteFormat::Hex,
+        "png" => voxport::PaletteFormat::Png,
+        _ => voxport::PaletteFormat::Jasc
     };
 
-    let neighbor: Option<dot_vox::Voxel>;
-    let idx = get_voxel_idx(&pos, size);
-    neighbor = voxels[idx];
-
-    return Ok(neighbor.is_some());
+    let in_data = voxport::load(in_file).unwrap_or_else(|err| exit_with_error(in_file, err));
+    let mut file = create_output(out_file);
+    voxport::export_palette(&in_data, format, matches.is_present("used"), &mut file).unwrap_or_else(|err| exit_with_error(out_file, err));
 }
 
-fn convert_meta_voxels(voxels: &Vec<Option<dot_vox::Voxel>>, size: &dot_vox::Size) -> Vec<Option<MetaVoxel>>
+/// Applies the palette file and palette index remapping given on the command line
+fn recolor(matches: &clap::ArgMatches, in_data: &mut dot_vox::DotVoxData)
 {
-    let mut mvoxels: Vec<Option<MetaVoxel>> = Vec::new();
-    let len: usize = (size.x as usize) * (size.y as usize) * (size.z as usize);
-    mvoxels.resize(len, None);
-    for (i, opt_voxel) in voxels.iter().enumerate()
+    if let Some(path) = matches.value_of("palette")
     {
-        match opt_voxel
+        let format = match Path::new(path).extension().unwrap_or_default().to_string_lossy().to_lowercase().as_str()
         {
-            Some(voxel) => {
-                let mut faces: u8 = 0;
-                let mut surrounded: bool = true;
-                // Need to add check to see if there are empty spaces in order to add a face
-                if voxel.x == 0 || !has_neighbor(voxels, voxel, size, -1, 0, 0).unwrap()// Left
-                {
-                    faces |= 1 << 0;
-                    surrounded = false;
-                }
-                
-                if voxel.y == 0 || !has_neighbor(voxels, voxel, size, 0, -1, 0).unwrap() // Back
-                {
-                    faces |= 1 << 1;
-                    surrounded = false;
-                }
-
-                if voxel.z == 0 || !has_neighbor(voxels, voxel, size, 0, 0, -1).unwrap() // Bottom
-                {
-                    faces |= 1 << 2;
-                    surrounded = false;
-                }
-
-                if ((voxel.x + 1) == (size.x as u8)) || !has_neighbor(voxels, voxel, size, 1, 0, 0).unwrap() // Right
-                {
-                    faces |= 1 << 3;
-                    surrounded = false;
-                }
-
-                if (voxel.y + 1) == (size.y as u8) || !has_neighbor(voxels, voxel, size, 0, 1, 0).unwrap()  // Front
-                {
-                    faces |= 1 << 4;
-                    surrounded = false;
-                }
-
-                if ((voxel.z + 1) == (size.z as u8)) || !has_neighbor(voxels, voxel, size, 0, 0, 1).unwrap() // Top
-                {
-                    faces |= 1 << 5;
-                    surrounded = false;
-                }
-
-                if !surrounded
-                {
-                    mvoxels[i] = Some(MetaVoxel {
-                        voxel: *voxel,
-                        faces
-                    });
-                }
-            }
-            None => {
-
-            }
-        }
+            "gpl" => voxport::PaletteFormat::Gimp,
+            "png" => voxport::PaletteFormat::Png,
+            _ => voxport::PaletteFormat::Jasc
+        };
+        let file = File::open(path).unwrap_or_else(|err| exit_with_error(path, VoxportError::NoInput(err)));
+        let colors = voxport::import_palette(format, std::io::BufReader::new(file)).unwrap_or_else(|err| exit_with_error(path, err));
+        voxport::apply_palette(in_data, &colors);
     }
 
-    return mvoxels;
+    // Already checked by the argument validator
+    let remap: std::collections::BTreeMap<u8, u8> = matches.values_of("remap")
+        .map(|values| values.filter_map(|value| app::parse_remap(value).ok()).collect())
+        .unwrap_or_default();
+    voxport::remap_palette_indices(in_data, &remap);
 }
 
-fn convert_triangles(mvoxels: &Vec<Option<MetaVoxel>>) -> Vec<Triangle>
+fn selection(matches: &clap::ArgMatches) -> voxport::Selection
 {
-    let mut triangles = Vec::new();
-
-    for opt_mvoxel in mvoxels
-    {
-        match opt_mvoxel
-        {
-            Some(mvoxel) => {
-                let vleft_back_top = Vertex {
-                    x: mvoxel.voxel.x as f32 + 0.0,
-                    y: mvoxel.voxel.y as f32 + 0.0,
-                    z: mvoxel.voxel.z as f32 + 1.0,
-                };
-
-                let vleft_front_top = Vertex {
-                    x: mvoxel.voxel.x as f32 + 0.0,
-                    y: mvoxel.voxel.y as f32 + 1.0,
-                    z: mvoxel.voxel.z as f32 + 1.0,
-                };
-
-                let vleft_back_bottom = Vertex {
-                    x: mvoxel.voxel.x as f32 + 0.0,
-                    y: mvoxel.voxel.y as f32 + 0.0,
-                    z: mvoxel.voxel.z as f32 + 0.0,
-                };
-
-                let vleft_front_bottom = Vertex {
-                    x: mvoxel.voxel.x as f32 + 0.0,
-                    y: mvoxel.voxel.y as f32 + 1.0,
-                    z: mvoxel.voxel.z as f32 + 0.0,
-                };
-
-                let vright_back_top = Vertex {
-                    x: mvoxel.voxel.x as f32 + 1.0,
-                    y: mvoxel.voxel.y as f32 + 0.0,
-                    z: mvoxel.voxel.z as f32 + 1.0,
-                };
-
-                let vright_front_top = Vertex {
-                    x: mvoxel.voxel.x as f32 + 1.0,
-                    y: mvoxel.voxel.y as f32 + 1.0,
-                    z: mvoxel.voxel.z as f32 + 1.0,
-                };
-
-                let vright_back_bottom = Vertex {
-                    x: mvoxel.voxel.x as f32 + 1.0,
-                    y: mvoxel.voxel.y as f32 + 0.0,
-                    z: mvoxel.voxel.z as f32 + 0.0,
-                };
-
-                let vright_front_bottom = Vertex {
-                    x: mvoxel.voxel.x as f32 + 1.0,
-                    y: mvoxel.voxel.y as f32 + 1.0,
-                    z: mvoxel.voxel.z as f32 + 0.0,
-                };
-
-                if mvoxel.has_left()
-                {
-                    let normal = Normal {
-                        x: 1.0,
-                        y: 0.0,
-                        z: 0.0
-                    };
-                    triangles.push(Triangle {
-                        normal,
-                        a: vleft_back_top,
-                        b: vleft_back_bottom,
-                        c: vleft_front_bottom
-                    });
-
-                    triangles.push(Triangle {
-                        normal,
-                        a: vleft_back_top,
-                        b: vleft_front_top,
-                        c: vleft_front_bottom
-                    });
-                }
-
-                if mvoxel.has_back()
-                {
-                    let normal = Normal {
-                        x: 0.0,
-                        y: 1.0,
-                        z: 0.0
-                    };
-                    triangles.push(Triangle {
-                        normal,
-                        a: vleft_back_top,
-                        b: vright_back_top,
-                        c: vright_back_bottom
-                    });
-
-                    triangles.push(Triangle {
-                        normal,
-                        a: vleft_back_top,
-                        b: vleft_back_bottom,
-                        c: vright_back_bottom
-                    });
-                }
-
-                if mvoxel.has_bottom()
-                {
-                    let normal = Normal {
-                        x: 0.0,
-                        y: 0.0,
-                        z: 1.0
-                    };
-                    triangles.push(Triangle {
-                        normal,
-                        a: vleft_back_bottom,
-                        b: vleft_front_bottom,
-                        c: vright_front_bottom
-                    });
-
-                    triangles.push(Triangle {
-                        normal,
-                        a: vleft_back_bottom,
-                        b: vright_back_bottom,
-                        c: vright_front_bottom
-                    });                    
-                }
-
-                if mvoxel.has_right()
-                {
-                    let normal = Normal {
-                        x: -1.0,
-                        y: 0.0,
-                        z: 0.0
-                    };
-                    triangles.push(Triangle {
-                        normal,
-                        a: vright_back_top,
-                        b: vright_back_bottom,
-                        c: vright_front_bottom
-                    });
-
-                    triangles.push(Triangle {
-                        normal,
-                        a: vright_back_top,
-                        b: vright_front_top,
-                        c: vright_front_bottom
-                    });
-                }
-
-                if mvoxel.has_front()
-                {
-                    let normal = Normal {
-                        x: 0.0,
-                        y: -1.0,
-                        z: 0.0
-                    };
-                    triangles.push(Triangle {
-                        normal,
-                        a: vleft_front_top,
-                        b: vright_front_top,
-                        c: vright_front_bottom
-                    });
-
-                    triangles.push(Triangle {
-                        normal,
-                        a: vleft_front_top,
-                        b: vleft_front_bottom,
-                        c: vright_front_bottom
-                    });
-                }
-
-                if mvoxel.has_top()
-                {
-                    let normal = Normal {
-                        x: 0.0,
-                        y: 0.0,
-                        z: -1.0
-                    };
-                    triangles.push(Triangle {
-                        normal,
-                        a: vleft_back_top,
-                        b: vleft_front_top,
-                        c: vright_front_top
-                    });
-
-                    triangles.push(Triangle {
-                        normal,
-                        a: vleft_back_top,
-                        b: vright_back_top,
-                        c: vright_front_top
-                    });
-                }
-            },
-            None => {
-
-            }
-        }
-    }
+    let values = |name: &str| -> Vec<String> {
+        matches.values_of(name).map(|values| values.map(|value| value.to_string()).collect()).unwrap_or_default()
+    };
 
-    return triangles;
+    return voxport::Selection {
+        // Already checked by the argument validator
+        models: values("models").iter().filter_map(|value| app::parse_model_range(value).ok()).collect(),
+        layers: values("layers"),
+        nodes: values("nodes"),
+        include_hidden: matches.is_present("include-hidden")
+    };
 }
 
-fn index_triangles(triangles: &Vec<Triangle>, vertices: &mut Vec<Vertex>, normals: &mut Vec<Normal>, idx_triangles: &mut Vec<IndexedTriangle>)
+fn main() 
 {
-    for triangle in triangles
+    let app = app::new_app();
+    let matches = app.get_matches();
+    if let Some(("palette", palette_matches)) = matches.subcommand()
     {
-        let normal_index: usize;
-        let a_position: usize;
-        let b_position: usize;
-        let c_position: usize;
-        
-        match vertices.iter().position(|&x| x == triangle.a)
-        {
-            Some(idx) => {
-                a_position = idx;
-            },
-            None => {
-                a_position = vertices.len();
-                vertices.push(triangle.a);
-            }
-        }
-
-        match vertices.iter().position(|&x| x == triangle.b)
-        {
-            Some(idx) => {
-                b_position = idx;
-            },
-            None => {
-                b_position = vertices.len();
-                vertices.push(triangle.b);
-            }
-        }
-
-        match vertices.iter().position(|&x| x == triangle.c)
-        {
-            Some(idx) => {
-                c_position = idx;
-            },
-            None => {
-                c_position = vertices.len();
-                vertices.push(triangle.c);
-            }
-        }
-
-        match normals.iter().position(|&x| x == triangle.normal)
-        {
-            Some(idx) => {
-                normal_index = idx;
-            },
-            None => {
-                normal_index = vertices.len();
-                normals.push(triangle.normal);
-            }
-        }
-
-        idx_triangles.push(IndexedTriangle {
-            normal: triangle.normal,
-            normal_index,
-            a: a_position,
-            b: b_position,
-            c: c_position
-        });
+        export_palette(palette_matches);
+        return;
     }
-}
-
-
-fn convert_vox_stl(ifpath: &str, ofpath: &str)
-{
-    use std::fs::OpenOptions;
-    let mut in_data = dot_vox::load(ifpath).unwrap();
-
-    let mut triangles: Vec<Triangle> = Vec::new();
-    for model in &mut in_data.models
+    let in_file = matches.value_of("input").unwrap_or(INPUT_FILEPATH);
+    let out_file = matches.value_of("output").unwrap_or_else(|| default_output(&matches));
+    let (mut in_data, scene) = voxport::load_scene(in_file).unwrap_or_else(|err| exit_with_error(in_file, err));
+    recolor(&matches, &mut in_data);
+    let scene = scene.select(&selection(&matches));
+    if scene.models().is_empty()
     {
-        let size = model.size;
-        let voxels = reorder_voxels(&mut model.voxels, &size);
-        let mvoxels = convert_meta_voxels(&voxels, &size);
-        triangles.append(&mut convert_triangles(&mvoxels));
+        exit_with_error(in_file, VoxportError::EmptySelection);
     }
+    let options = mesh_options(&matches);
 
-    let stl_triangles: Vec<stl_io::Triangle>;
-    stl_triangles = triangles.iter().map(|triangle| (*triangle).into()).collect::<Vec<_>>();
-    let mut file = OpenOptions::new().write(true).create(true).open(ofpath).unwrap();
-    stl_io::write_stl(&mut file, stl_triangles.iter()).unwrap();
-}
-
-fn convert_vox_dae(ifpath: &str, ofpath: &str)
-{
-    let mut in_data = dot_vox::load(ifpath).unwrap();
-
-    let collada: collada_io::collada::Collada;
-    let mut geometries: Vec<collada_io::geometry::Geometry> = Vec::new();
-    for model in &mut in_data.models
+    let split = match matches.value_of("split")
     {
-        let size = model.size;
-        let voxels = reorder_voxels(&mut model.voxels, &size);
-        let mvoxels = convert_meta_voxels(&voxels, &size);
-        let triangles = convert_triangles(&mvoxels);
-        let mut vertices: Vec<Vertex> = Vec::new();
-        let mut normals: Vec<Normal> = Vec::new();
-        let mut indexed_triangles: Vec<IndexedTriangle> = Vec::new();
-        index_triangles(&triangles, &mut vertices, &mut normals, &mut indexed_triangles);
+        Some("model") => Some(voxport::SplitMode::Model),
+        Some("layer") => Some(voxport::SplitMode::Layer),
+        Some("node") => Some(voxport::SplitMode::Node),
+        _ => None
+    };
 
-        let mut primitive: Vec<usize> = Vec::new();
-        primitive.reserve(indexed_triangles.len() * 3);
-        let mut mesh_positions: Vec<f32> = Vec::new();
-        mesh_positions.reserve(vertices.len() * 3);
+    match matches.value_of("animation")
+    {
+        Some("single") => {
+            export_animation(&matches, &in_data, &scene.frames(), &options, out_file);
+            return;
+        },
+        Some("sequence") => {
+            let template = matches.value_of("name-template").unwrap_or_default();
+            for (index, frame) in scene.frames().iter().enumerate()
+            {
+                let frame_file = part_path(template, out_file, index, &format!("Frame-{}", index));
+                export(&matches, &in_data, frame, &options, &frame_file);
+            }
+            return;
+        },
+        _ => {}
+    }
 
-        // TODO: Add normals
-        for idx_triangle in indexed_triangles
+    if matches.is_present("split-colors")
+    {
+        let groups = match matches.value_of("color-groups")
         {
-            primitive.push(idx_triangle.a);
-            primitive.push(idx_triangle.b);
-            primitive.push(idx_triangle.c);
-        }
-
-        for vertex in vertices
+            Some(path) => {
+                let text = std::fs::read_to_string(path).unwrap_or_else(|err| exit_with_error(path, VoxportError::NoInput(err)));
+                app::parse_color_groups(&text).unwrap_or_else(|err| exit_with_error(path, VoxportError::InvalidInput(err)))
+            },
+            None => std::collections::BTreeMap::new()
+        };
+        for part in voxport::split_by_color(&in_data, &scene, &groups)
         {
-            mesh_positions.push(vertex.x);
-            mesh_positions.push(vertex.y);
-            mesh_positions.push(vertex.z);
+            let part_file = match part.group
+            {
+                voxport::ColorGroup::Group(number) => part_path("{stem}_group{index}.{ext}", out_file, number, ""),
+                voxport::ColorGroup::Index(index) => part_path("{stem}_color{index}.{ext}", out_file, index as usize, "")
+            };
+            export(&matches, &part.in_data, &scene, &options, &part_file);
         }
-
-        geometries.push(collada_io::geometry::Geometry {
-            id: Some("Voxel-mesh".to_string()),
-            name: Some("Voxel".to_string()),
-            mesh: collada_io::geometry::Mesh {
-                triangles: collada_io::geometry::Triangles {
-                    vertices: "#Cube-mesh-vertices".to_string(),
-                    normals: None,
-                    tex_vertices: None,
-                    primitive: Some(primitive),
-                    material: None
-                },
-                vertices: collada_io::geometry::Vertices {
-                    id: "Voxel-mesh-vertices".to_string(),
-                    name: None,
-                    source: "#Voxel-mesh-positions".to_string()
-                },
-                sources: vec! {
-                    collada_io::geometry::Source {
-                        id: "Voxel-mesh-positions".to_string(),
-                        float_array: collada_io::geometry::FloatArray {
-                            id: "Voxel-mesh-positions-array".to_string(),    
-                            data: mesh_positions
-                        },
-                        accessor: collada_io::geometry::Accessor {
-                            params: vec! { "X".to_string(), "Y".to_string(), "Z".to_string() }
-                        }
-                    },
-                }
-            }
-        });   
+        return;
     }
 
-    let mut file = File::create(ofpath).unwrap();
-
-    collada = collada_io::collada::Collada {
-        scene: Some(collada_io::scene::Scene {
-            visual_scenes: vec!{
-                "#Scene".to_string()
+    match split
+    {
+        Some(mode) => {
+            let template = matches.value_of("name-template").unwrap_or_default();
+            let parts = scene.split(mode);
+            if parts.is_empty()
+            {
+                eprintln!("voxport: {}: no {} to split by, nothing written", in_file, matches.value_of("split").unwrap_or_default());
             }
-        }),
-        visual_scenes: Some(vec!{
-            collada_io::scene::VisualScene {
-                id: "Scene".to_string(),
-                name: "Scene".to_string(),
-                nodes: vec!{
-                    collada_io::scene::Node {
-                        id: "Voxel".to_string(),
-                        name: "Voxel".to_string(),
-                        transformation_elements: vec!{
-                            collada_io::scene::TransformationElement::Matrix {
-                                sid: "transform".to_string(),
-                                matrix: vec! {
-                                    1.0, 0.0, 0.0, 0.0, 
-                                    0.0, 1.0, 0.0, 0.0, 
-                                    0.0, 0.0, 1.0, 0.0,
-                                    0.0, 0.0, 0.0, 1.0,
-                                }
-                            }
-                        },
-                        instances: vec!{
-                            collada_io::scene::Instance::Geometry {
-                                url: "#Voxel-mesh".to_string(),
-                                name: Some("Voxel".to_string()),
-                                sid: None,
-                                bind_material: None
-                            }
-                        }
-                    }
-                }
+            for (index, part) in parts.iter().enumerate()
+            {
+                let part_file = part_path(template, out_file, index, &part.name);
+                export(&matches, &in_data, &part.scene, &options, &part_file);
             }
-        }),
-        asset: collada_io::meta::Asset::default(),
-        geometries: Some(geometries)
-    };
-
-    collada.write_to(&mut file).unwrap();
-}
-
-fn _export_jasc_palette(ifpath: &str, ofpath: &str) -> std::io::Result<()>
-{
-    let in_data = dot_vox::load(ifpath).unwrap();
-    let file = File::create(ofpath)?;
-    let mut file = LineWriter::new(file);
-
-    let len_str: String = in_data.palette.len().to_string() + "\n";
-    file.write_all(b"JASC\n")?;
-    file.write_all(b"0100\n")?;
-    file.write_all(len_str.as_bytes())?;
-    for element in &in_data.palette
-    {
-        let color: Color = (*element).into();
-        let color_str: String = color.into();
-        file.write_all((color_str + "\n").as_bytes())?;
+        },
+        None => {
+            export(&matches, &in_data, &scene, &options, out_file);
+        }
     }
-
-    Ok(())
 }
-
-fn main() 
-{
-    let app = app::new_app();
-    let matches = app.get_matches();
-    let in_file = matches.value_of("input").unwrap_or(INPUT_FILEPATH);
-    let out_file: &str;
-
-    if matches.is_present("stl")
-    {
-        out_file = matches.value_of("output").unwrap_or(OUTPUT_STL_FILEPATH);
-        convert_vox_stl(in_file, out_file);
-    } else if matches.is_present("dae")
-    {
-        out_file = matches.value_of("output").unwrap_or(OUTPUT_DAE_FILEPATH);
-        convert_vox_dae(in_file, out_file);
-    } else {
-        // Need either stl or dae so not sure what to do here
-    }
-}
\ No newline at end of file
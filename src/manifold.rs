@@ -0,0 +1,264 @@
+//! Manifold meshing: emits every exposed voxel face like the naive mesher, but
+//! splits the vertices and edges where voxels touch only at an edge or a corner,
+//! so that slicers see a watertight 2-manifold surface.
+
+use std::collections::HashMap;
+
+use crate::mesh::{face_corners, face_normal, Face, MetaVoxel, Triangle, Vertex, FACES};
+
+/// Distance in voxels by which the copies of a split vertex or edge are moved
+/// apart, each into the side of the surface it belongs to
+pub const SPLIT_OFFSET: f32 = 1.0 / 64.0;
+
+type Point = [i32; 3];
+
+/// An exposed voxel face, between the filled `cell` and the empty cell in front of it
+struct Quad
+{
+    cell: Point,
+    face: Face,
+    corners: [Point; 4],
+    color_index: u8
+}
+
+impl Quad
+{
+    fn empty_cell(&self) -> Point
+    {
+        let normal = face_normal(self.face);
+        return [
+            self.cell[0] + normal.x as i32,
+            self.cell[1] + normal.y as i32,
+            self.cell[2] + normal.z as i32
+        ];
+    }
+}
+
+fn find(parents: &mut [usize], mut node: usize) -> usize
+{
+    while parents[node] != node
+    {
+        parents[node] = parents[parents[node]];
+        node = parents[node];
+    }
+    return node;
+}
+
+fn union(parents: &mut [usize], a: usize, b: usize)
+{
+    let a = find(parents, a);
+    let b = find(parents, b);
+    parents[a] = b;
+}
+
+fn to_vertex(point: Point) -> Vertex
+{
+    return Vertex {
+        x: point[0] as f32,
+        y: point[1] as f32,
+        z: point[2] as f32
+    };
+}
+
+/// `from` moved by `SPLIT_OFFSET` toward the average center of `cells`, or
+/// away from it if `away` is set. `None` if the center is at `from`.
+fn offset_toward(from: Vertex, cells: &[Point], away: bool) -> Option<Vertex>
+{
+    let count = cells.len() as f32;
+    let mut direction = [0.0f32; 3];
+    for cell in cells
+    {
+        direction[0] += (cell[0] as f32 + 0.5 - from.x) / count;
+        direction[1] += (cell[1] as f32 + 0.5 - from.y) / count;
+        direction[2] += (cell[2] as f32 + 0.5 - from.z) / count;
+    }
+
+    let length = (direction[0] * direction[0] + direction[1] * direction[1] + direction[2] * direction[2]).sqrt();
+    if length < 1e-6
+    {
+        return None;
+    }
+    let scale = if away { -SPLIT_OFFSET } else { SPLIT_OFFSET } / length;
+    return Some(Vertex {
+        x: from.x + direction[0] * scale,
+        y: from.y + direction[1] * scale,
+        z: from.z + direction[2] * scale
+    });
+}
+
+/// Emits the exposed faces of `mvoxels` as a 2-manifold surface.
+///
+/// Along an edge shared by two voxels touching only there, four faces meet. They
+/// are paired by voxel, and each pair gets its own midpoint on the edge, moved
+/// into its voxel. Around every vertex the faces then form closed fans. A vertex
+/// with more than one fan, like the corner two voxels touch at, gets a copy per
+/// fan, moved toward the filled cells of the fan, or toward its empty cells if
+/// those are fewer.
+pub fn convert_manifold_triangles(mvoxels: &[Option<MetaVoxel>]) -> Vec<Triangle>
+{
+    let mut quads: Vec<Quad> = Vec::new();
+    for opt_mvoxel in mvoxels
+    {
+        match opt_mvoxel
+        {
+            Some(mvoxel) => {
+                let cell = [mvoxel.voxel.x as i32, mvoxel.voxel.y as i32, mvoxel.voxel.z as i32];
+                let lo = to_vertex(cell);
+                let hi = to_vertex([cell[0] + 1, cell[1] + 1, cell[2] + 1]);
+                for face in FACES.iter()
+                {
+                    if mvoxel.has_face(*face)
+                    {
+                        let corners = face_corners(*face, lo, hi);
+                        let point = |vertex: Vertex| [vertex.x as i32, vertex.y as i32, vertex.z as i32];
+                        quads.push(Quad {
+                            cell,
+                            face: *face,
+                            corners: [point(corners[0]), point(corners[1]), point(corners[2]), point(corners[3])],
+                            color_index: mvoxel.voxel.i
+                        });
+                    }
+                }
+            },
+            None => {}
+        }
+    }
+
+    // Corner `k` of quad `q` and its edge from corner `k` to `k + 1` are both
+    // numbered `q * 4 + k`
+    let mut edges: HashMap<(Point, Point), Vec<usize>> = HashMap::new();
+    for (q, quad) in quads.iter().enumerate()
+    {
+        for k in 0..4
+        {
+            let (start, end) = (quad.corners[k], quad.corners[(k + 1) % 4]);
+            let key = if start < end { (start, end) } else { (end, start) };
+            edges.entry(key).or_default().push(q * 4 + k);
+        }
+    }
+
+    // Joins the corners of faces sharing an edge into fans, and gives the edges
+    // shared by four faces a midpoint for each pair of them
+    let mut parents: Vec<usize> = (0..quads.len() * 4).collect();
+    let mut midpoints: HashMap<usize, Vertex> = HashMap::new();
+    let corner_at = |edge: usize, point: Point| -> usize {
+        let q = edge / 4;
+        let k = if quads[q].corners[edge % 4] == point { edge % 4 } else { (edge % 4 + 1) % 4 };
+        return q * 4 + k;
+    };
+    for ((start, end), shared) in &edges
+    {
+        let mut pairs: Vec<(usize, usize)> = Vec::new();
+        for (i, a) in shared.iter().enumerate()
+        {
+            for b in &shared[i + 1..]
+            {
+                if shared.len() == 2 || quads[a / 4].cell == quads[b / 4].cell
+                {
+                    pairs.push((*a, *b));
+                }
+            }
+        }
+
+        for (a, b) in pairs
+        {
+            union(&mut parents, corner_at(a, *start), corner_at(b, *start));
+            union(&mut parents, corner_at(a, *end), corner_at(b, *end));
+            if shared.len() == 4
+            {
+                let middle = Vertex {
+                    x: (start[0] + end[0]) as f32 / 2.0,
+                    y: (start[1] + end[1]) as f32 / 2.0,
+                    z: (start[2] + end[2]) as f32 / 2.0
+                };
+                let midpoint = offset_toward(middle, &[quads[a / 4].cell], false).unwrap_or(middle);
+                midpoints.insert(a, midpoint);
+                midpoints.insert(b, midpoint);
+            }
+        }
+    }
+
+    // Corners of every fan around every vertex
+    let mut fans: HashMap<Point, HashMap<usize, Vec<usize>>> = HashMap::new();
+    for corner in 0..quads.len() * 4
+    {
+        let root = find(&mut parents, corner);
+        fans.entry(quads[corner / 4].corners[corner % 4]).or_default().entry(root).or_default().push(corner);
+    }
+
+    let mut positions: HashMap<usize, Vertex> = HashMap::new();
+    for (point, point_fans) in &fans
+    {
+        if point_fans.len() < 2
+        {
+            continue;
+        }
+
+        let vertex = to_vertex(*point);
+        for (root, corners) in point_fans
+        {
+            let mut filled: Vec<Point> = corners.iter().map(|corner| quads[corner / 4].cell).collect();
+            let mut empty: Vec<Point> = corners.iter().map(|corner| quads[corner / 4].empty_cell()).collect();
+            for cells in [&mut filled, &mut empty].iter_mut()
+            {
+                cells.sort_unstable();
+                cells.dedup();
+            }
+
+            let (fewer, more) = if filled.len() <= empty.len() { (&filled, &empty) } else { (&empty, &filled) };
+            let position = offset_toward(vertex, fewer, false)
+                .or_else(|| offset_toward(vertex, more, true))
+                .unwrap_or(vertex);
+            positions.insert(*root, position);
+        }
+    }
+
+    let mut triangles: Vec<Triangle> = Vec::new();
+    for (q, quad) in quads.iter().enumerate()
+    {
+        let normal = face_normal(quad.face);
+        let mut ring: Vec<Vertex> = Vec::with_capacity(8);
+        for k in 0..4
+        {
+            let root = find(&mut parents, q * 4 + k);
+            ring.push(positions.get(&root).copied().unwrap_or_else(|| to_vertex(quad.corners[k])));
+            match midpoints.get(&(q * 4 + k))
+            {
+                Some(midpoint) => {
+                    ring.push(*midpoint);
+                },
+                None => {}
+            }
+        }
+
+        let mut push = |a: Vertex, b: Vertex, c: Vertex| {
+            triangles.push(Triangle {
+                normal,
+                a,
+                b,
+                c,
+                color_index: quad.color_index
+            });
+        };
+        if ring.len() == 4
+        {
+            push(ring[0], ring[1], ring[2]);
+            push(ring[0], ring[2], ring[3]);
+        }
+        else
+        {
+            // Fans out from the center, as the midpoints aren't in the plane of the face
+            let center = Vertex {
+                x: quad.corners.iter().map(|corner| corner[0] as f32).sum::<f32>() / 4.0,
+                y: quad.corners.iter().map(|corner| corner[1] as f32).sum::<f32>() / 4.0,
+                z: quad.corners.iter().map(|corner| corner[2] as f32).sum::<f32>() / 4.0
+            };
+            for i in 0..ring.len()
+            {
+                push(center, ring[i], ring[(i + 1) % ring.len()]);
+            }
+        }
+    }
+
+    return triangles;
+}
@@ -0,0 +1,114 @@
+//! MagicaVoxel `MATL` materials, translated into physically based parameters.
+
+use std::collections::HashMap;
+
+/// Index of refraction of glass without an `_ior` property
+pub const DEFAULT_IOR: f32 = 1.5;
+
+/// Material of a palette index, with every factor in `0.0..=1.0`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct VoxMaterial
+{
+    pub metallic: f32,
+    pub roughness: f32,
+    /// Strength of the light emitted in the palette color
+    pub emission: f32,
+    /// How much light passes through, as for glass
+    pub transmission: f32,
+    /// Alpha the surface is blended with
+    pub opacity: f32,
+    pub ior: f32
+}
+
+impl Default for VoxMaterial
+{
+    /// The plain diffuse material of palette indices without a `MATL` chunk
+    fn default() -> Self
+    {
+        Self {
+            metallic: 0.0,
+            roughness: 1.0,
+            emission: 0.0,
+            transmission: 0.0,
+            opacity: 1.0,
+            ior: DEFAULT_IOR
+        }
+    }
+}
+
+impl VoxMaterial
+{
+    /// Reads the properties of a `MATL` chunk. Files before MagicaVoxel 0.99.5
+    /// only give the strength of metal, glass and emit materials as `_weight`,
+    /// later ones as `_metal`, `_trans` and `_emit`. `_ior` is stored minus one.
+    pub fn from_properties(properties: &HashMap<String, String>) -> Self
+    {
+        let value = |key: &str| -> Option<f32> {
+            return properties.get(key)
+                .and_then(|value| value.trim().parse::<f32>().ok())
+                .filter(|value| value.is_finite())
+                .map(|value| value.clamp(0.0, 1.0));
+        };
+        let weight = value("_weight").unwrap_or(1.0);
+        let transparency = value("_trans").or_else(|| value("_alpha"));
+
+        let mut material = Self::default();
+        match properties.get("_type").map(|kind| kind.as_str())
+        {
+            Some("_metal") => {
+                material.metallic = value("_metal").unwrap_or(weight);
+                material.roughness = value("_rough").unwrap_or(material.roughness);
+            },
+            Some("_glass") => {
+                material.transmission = transparency.unwrap_or(weight);
+                material.roughness = value("_rough").unwrap_or(material.roughness);
+            },
+            Some("_emit") => {
+                material.emission = value("_emit").unwrap_or(weight);
+            },
+            // Blends every property at once, with the transparency alpha blended
+            Some("_blend") => {
+                material.metallic = value("_metal").unwrap_or(0.0);
+                material.roughness = value("_rough").unwrap_or(material.roughness);
+                material.emission = value("_emit").unwrap_or(0.0);
+                material.opacity = 1.0 - transparency.unwrap_or(0.0);
+            },
+            _ => {}
+        }
+        match properties.get("_ior").and_then(|ior| ior.trim().parse::<f32>().ok())
+        {
+            Some(ior) if ior.is_finite() && ior >= 0.0 => {
+                material.ior = 1.0 + ior;
+            },
+            _ => {}
+        }
+
+        return material;
+    }
+
+    /// Whether the material looks like the plain diffuse default. The index of
+    /// refraction only matters for materials letting light through.
+    pub fn is_default(&self) -> bool
+    {
+        let default = Self::default();
+        return Self {
+            ior: default.ior,
+            ..*self
+        } == default;
+    }
+}
+
+/// The material of every palette index of `in_data`, indexed like `Voxel.i`.
+/// `MATL` ids count palette entries from one, as the color indices in the file do.
+pub fn palette_materials(in_data: &dot_vox::DotVoxData) -> Vec<VoxMaterial>
+{
+    let mut materials = vec![VoxMaterial::default(); 256];
+    for material in &in_data.materials
+    {
+        if material.id >= 1 && material.id <= 256
+        {
+            materials[material.id as usize - 1] = VoxMaterial::from_properties(&material.properties);
+        }
+    }
+    return materials;
+}
@@ -0,0 +1,668 @@
+//! Conversion of MagicaVoxel models into triangle meshes.
+
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+use crate::error::{Result, VoxportError};
+use crate::hollow::Hollow;
+use crate::palette::PaletteTexture;
+use crate::scene::{Instance, Pivot, Scene};
+use crate::units::VoxelSize;
+use crate::weld::Welder;
+
+/// A voxel along with a bitmask of which of its faces are exposed
+#[derive(Copy, Clone, Debug)]
+pub struct MetaVoxel
+{
+    pub voxel: dot_vox::Voxel,
+    pub faces: u8
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Vertex
+{
+    pub x: f32,
+    pub y: f32,
+    pub z: f32
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Normal
+{
+    pub x: f32,
+    pub y: f32,
+    pub z: f32
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Triangle
+{
+    pub normal: Normal,
+    pub a: Vertex,
+    pub b: Vertex,
+    pub c: Vertex,
+    pub color_index: u8 // Palette index of the voxel the face belongs to
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct IndexedTriangle
+{
+    pub normal: Normal, // STL
+    pub normal_index: usize, // DAE
+    pub a: usize,
+    pub b: usize,
+    pub c: usize,
+    pub color_index: u8
+}
+
+/// The six faces of a voxel, numbered by their bit in `MetaVoxel::faces`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Face
+{
+    Left = 0,
+    Back = 1,
+    Bottom = 2,
+    Right = 3,
+    Front = 4,
+    Top = 5
+}
+
+pub const FACES: [Face; 6] = [Face::Left, Face::Back, Face::Bottom, Face::Right, Face::Front, Face::Top];
+
+/// Which meshing algorithm turns exposed voxel faces into triangles
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Mesher
+{
+    /// Two triangles for every exposed face of every voxel
+    Naive,
+    /// Merges adjacent coplanar faces into maximal rectangles. Unless
+    /// `merge_colors` is set, only faces sharing a palette index are merged.
+    /// Large faces meet small ones in T-junctions, so the surface isn't closed.
+    Greedy {
+        merge_colors: bool
+    },
+    /// Every exposed face like `Naive`, with the vertices and edges of voxels
+    /// touching only at an edge or a corner split apart, so that every edge
+    /// is shared by exactly two triangles. Meant for 3D printing.
+    Manifold
+}
+
+/// Options controlling how a model is meshed
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeshOptions
+{
+    /// Greedy by default. STL files for printing want `Naive` or `Manifold`.
+    pub mesher: Mesher,
+    /// Maps faces onto a palette texture, in the formats supporting it
+    pub texture: Option<PaletteTexture>,
+    /// Treats empty cells that can't be reached from outside the model as
+    /// filled, dropping the walls of sealed cavities nobody gets to see
+    pub fill_cavities: bool,
+    /// Hollows the models for 3D printing, leaving walls around a cavity
+    pub hollow: Option<Hollow>,
+    /// Scales positions from one unit per voxel to the length of a voxel in
+    /// its unit. glTF positions are always in meters.
+    pub voxel_size: Option<VoxelSize>,
+    /// Moves every model so that its scene node places this point of it
+    /// instead of the center MagicaVoxel places
+    pub pivot: Pivot
+}
+
+impl Default for MeshOptions
+{
+    fn default() -> Self
+    {
+        Self {
+            mesher: Mesher::Greedy {
+                merge_colors: false
+            },
+            texture: None,
+            fill_cavities: false,
+            hollow: None,
+            voxel_size: None,
+            pivot: Pivot::MagicaVoxel
+        }
+    }
+}
+
+impl MeshOptions
+{
+    /// Factor taking positions from one unit per voxel to the unit of the voxel
+    /// size. Formats scale positions as they write them, after welding vertices.
+    pub fn position_scale(&self) -> f32
+    {
+        return self.voxel_size.map(|voxel_size| voxel_size.length).unwrap_or(1.0);
+    }
+}
+
+impl MetaVoxel
+{
+    /// Whether `face` of the voxel is exposed
+    pub fn has_face(&self, face: Face) -> bool
+    {
+        return ((self.faces >> (face as u8)) & 1) == 1;
+    }
+}
+
+pub fn get_voxel_idx(vox: &dot_vox::Voxel, size: &dot_vox::Size) -> usize
+{
+    return (vox.x as usize) + 
+           ((vox.y as usize) * (size.x as usize)) + 
+           ((vox.z as usize) * (size.x as usize) * (size.y as usize));
+}
+
+/// Turns the sparse voxel list of a model into a dense grid indexed by `get_voxel_idx`
+pub fn reorder_voxels(voxels: &[dot_vox::Voxel], size: &dot_vox::Size) -> Result<Vec<Option<dot_vox::Voxel>>>
+{
+    let mut ret_voxels: Vec<Option<dot_vox::Voxel>> = Vec::new();
+    let len: usize = (size.x as usize) * (size.y as usize) * (size.z as usize);
+    ret_voxels.resize(len, None);
+    for voxel in voxels
+    {
+        if (voxel.x as u32) >= size.x || (voxel.y as u32) >= size.y || (voxel.z as u32) >= size.z
+        {
+            return Err(VoxportError::UnsupportedModel(format!(
+                "voxel at ({}, {}, {}) lies outside the model size {}x{}x{}",
+                voxel.x, voxel.y, voxel.z, size.x, size.y, size.z
+            )));
+        }
+
+        let idx = get_voxel_idx(voxel, size);
+        ret_voxels[idx] = Some(*voxel);
+    }
+
+    return Ok(ret_voxels);
+}
+
+/// Marks the empty cells of `voxels` that can be reached from outside the
+/// model bounds through the faces of other empty cells
+pub fn outside_cells(voxels: &[Option<dot_vox::Voxel>], size: &dot_vox::Size) -> Vec<bool>
+{
+    let dims = [size.x as usize, size.y as usize, size.z as usize];
+    let index = |pos: [usize; 3]| pos[0] + pos[1] * dims[0] + pos[2] * dims[0] * dims[1];
+    let mut outside: Vec<bool> = vec![false; voxels.len()];
+    let mut stack: Vec<[usize; 3]> = Vec::new();
+
+    // Empty cells on the bounds are next to the outside
+    for z in 0..dims[2]
+    {
+        for y in 0..dims[1]
+        {
+            for x in 0..dims[0]
+            {
+                let on_bounds = x == 0 || y == 0 || z == 0 || x + 1 == dims[0] || y + 1 == dims[1] || z + 1 == dims[2];
+                if on_bounds && voxels[index([x, y, z])].is_none()
+                {
+                    outside[index([x, y, z])] = true;
+                    stack.push([x, y, z]);
+                }
+            }
+        }
+    }
+
+    while let Some(pos) = stack.pop()
+    {
+        for axis in 0..3
+        {
+            let mut neighbors: Vec<[usize; 3]> = Vec::with_capacity(2);
+            if pos[axis] > 0
+            {
+                let mut neighbor = pos;
+                neighbor[axis] -= 1;
+                neighbors.push(neighbor);
+            }
+            if pos[axis] + 1 < dims[axis]
+            {
+                let mut neighbor = pos;
+                neighbor[axis] += 1;
+                neighbors.push(neighbor);
+            }
+
+            for neighbor in neighbors
+            {
+                let idx = index(neighbor);
+                if !outside[idx] && voxels[idx].is_none()
+                {
+                    outside[idx] = true;
+                    stack.push(neighbor);
+                }
+            }
+        }
+    }
+
+    return outside;
+}
+
+/// Fills the empty cells of `voxels` that can't be reached from outside the
+/// model bounds. Every filled cell takes the palette index of the nearest voxel
+/// of the cavity wall, so filling adds no colors to the model.
+pub fn fill_cavities(voxels: &mut [Option<dot_vox::Voxel>], size: &dot_vox::Size)
+{
+    let outside = outside_cells(voxels, size);
+    let dims = [size.x as i64, size.y as i64, size.z as i64];
+
+    // Spreads out from every voxel at once, one step through a face at a time
+    let mut queue: VecDeque<usize> = (0..voxels.len()).filter(|idx| voxels[*idx].is_some()).collect();
+    while let Some(idx) = queue.pop_front()
+    {
+        let voxel = voxel_at(idx, size, 0);
+        let color_index = voxels[idx].map(|voxel| voxel.i).unwrap_or_default();
+        for axis in 0..3
+        {
+            for offset in [-1i64, 1].iter()
+            {
+                let mut pos = [voxel.x as i64, voxel.y as i64, voxel.z as i64];
+                pos[axis] += offset;
+                if pos[axis] < 0 || pos[axis] >= dims[axis]
+                {
+                    continue;
+                }
+
+                let neighbor = dot_vox::Voxel { x: pos[0] as u8, y: pos[1] as u8, z: pos[2] as u8, i: color_index };
+                let neighbor_idx = get_voxel_idx(&neighbor, size);
+                if voxels[neighbor_idx].is_none() && !outside[neighbor_idx]
+                {
+                    voxels[neighbor_idx] = Some(neighbor);
+                    queue.push_back(neighbor_idx);
+                }
+            }
+        }
+    }
+}
+
+/// The voxel with palette index `i` at `idx` of a grid indexed by `get_voxel_idx`
+pub fn voxel_at(idx: usize, size: &dot_vox::Size, i: u8) -> dot_vox::Voxel
+{
+    let (sx, sy) = (size.x as usize, size.y as usize);
+    return dot_vox::Voxel {
+        x: (idx % sx) as u8,
+        y: (idx / sx % sy) as u8,
+        z: (idx / (sx * sy)) as u8,
+        i
+    };
+}
+
+/// Checks whether the cell offset by (`x`, `y`, `z`) from `voxel` is filled.
+/// Cells outside of the model bounds are always empty.
+pub fn has_neighbor(voxels: &[Option<dot_vox::Voxel>], voxel: &dot_vox::Voxel, size: &dot_vox:: Size, x: i16, y: i16, z: i16) -> bool
+{
+    use std::convert::TryFrom;
+    let vx: i16 = voxel.x.into();
+    let vy: i16 = voxel.y.into();
+    let vz: i16 = voxel.z.into();
+
+    let (nx, ny, nz) = match (u8::try_from(vx + x), u8::try_from(vy + y), u8::try_from(vz + z))
+    {
+        (Ok(nx), Ok(ny), Ok(nz)) => (nx, ny, nz),
+        _ => return false
+    };
+
+    if (nx as u32) >= size.x || (ny as u32) >= size.y || (nz as u32) >= size.z
+    {
+        return false;
+    }
+
+    let pos = dot_vox::Voxel {
+        i: voxel.i,
+        x: nx,
+        y: ny,
+        z: nz
+    };
+
+    let idx = get_voxel_idx(&pos, size);
+    return voxels[idx].is_some();
+}
+
+/// Finds the exposed faces of every voxel, dropping voxels that are fully surrounded
+pub fn convert_meta_voxels(voxels: &[Option<dot_vox::Voxel>], size: &dot_vox::Size) -> Vec<Option<MetaVoxel>>
+{
+    let mut mvoxels: Vec<Option<MetaVoxel>> = Vec::new();
+    let len: usize = (size.x as usize) * (size.y as usize) * (size.z as usize);
+    mvoxels.resize(len, None);
+    for (i, opt_voxel) in voxels.iter().enumerate()
+    {
+        match opt_voxel
+        {
+            Some(voxel) => {
+                let mut faces: u8 = 0;
+                let mut surrounded: bool = true;
+                // Need to add check to see if there are empty spaces in order to add a face
+                if !has_neighbor(voxels, voxel, size, -1, 0, 0) // Left
+                {
+                    faces |= 1 << 0;
+                    surrounded = false;
+                }
+                
+                if !has_neighbor(voxels, voxel, size, 0, -1, 0) // Back
+                {
+                    faces |= 1 << 1;
+                    surrounded = false;
+                }
+
+                if !has_neighbor(voxels, voxel, size, 0, 0, -1) // Bottom
+                {
+                    faces |= 1 << 2;
+                    surrounded = false;
+                }
+
+                if !has_neighbor(voxels, voxel, size, 1, 0, 0) // Right
+                {
+                    faces |= 1 << 3;
+                    surrounded = false;
+                }
+
+                if !has_neighbor(voxels, voxel, size, 0, 1, 0) // Front
+                {
+                    faces |= 1 << 4;
+                    surrounded = false;
+                }
+
+                if !has_neighbor(voxels, voxel, size, 0, 0, 1) // Top
+                {
+                    faces |= 1 << 5;
+                    surrounded = false;
+                }
+
+                if !surrounded
+                {
+                    mvoxels[i] = Some(MetaVoxel {
+                        voxel: *voxel,
+                        faces
+                    });
+                }
+            }
+            None => {
+
+            }
+        }
+    }
+
+    return mvoxels;
+}
+
+/// Outward facing unit normal of `face`
+pub fn face_normal(face: Face) -> Normal
+{
+    let (x, y, z) = match face
+    {
+        Face::Left => (-1.0, 0.0, 0.0),
+        Face::Back => (0.0, -1.0, 0.0),
+        Face::Bottom => (0.0, 0.0, -1.0),
+        Face::Right => (1.0, 0.0, 0.0),
+        Face::Front => (0.0, 1.0, 0.0),
+        Face::Top => (0.0, 0.0, 1.0)
+    };
+
+    return Normal {
+        x,
+        y,
+        z
+    };
+}
+
+/// Corners of `face` of the axis-aligned box spanning `lo` to `hi`, in
+/// counter-clockwise order when viewed from outside the box
+pub fn face_corners(face: Face, lo: Vertex, hi: Vertex) -> [Vertex; 4]
+{
+    let vleft_back_top = Vertex { x: lo.x, y: lo.y, z: hi.z };
+    let vleft_front_top = Vertex { x: lo.x, y: hi.y, z: hi.z };
+    let vleft_back_bottom = Vertex { x: lo.x, y: lo.y, z: lo.z };
+    let vleft_front_bottom = Vertex { x: lo.x, y: hi.y, z: lo.z };
+    let vright_back_top = Vertex { x: hi.x, y: lo.y, z: hi.z };
+    let vright_front_top = Vertex { x: hi.x, y: hi.y, z: hi.z };
+    let vright_back_bottom = Vertex { x: hi.x, y: lo.y, z: lo.z };
+    let vright_front_bottom = Vertex { x: hi.x, y: hi.y, z: lo.z };
+
+    let corners: [Vertex; 4] = match face
+    {
+        Face::Left => [vleft_back_bottom, vleft_back_top, vleft_front_top, vleft_front_bottom],
+        Face::Back => [vleft_back_bottom, vright_back_bottom, vright_back_top, vleft_back_top],
+        Face::Bottom => [vleft_back_bottom, vleft_front_bottom, vright_front_bottom, vright_back_bottom],
+        Face::Right => [vright_back_bottom, vright_front_bottom, vright_front_top, vright_back_top],
+        Face::Front => [vleft_front_bottom, vleft_front_top, vright_front_top, vright_front_bottom],
+        Face::Top => [vleft_back_top, vright_back_top, vright_front_top, vleft_front_top]
+    };
+    return corners;
+}
+
+/// Emits the two triangles of `face` of the axis-aligned box spanning `lo` to `hi`.
+/// Both triangles wind counter-clockwise when viewed from outside the box.
+pub fn push_face(triangles: &mut Vec<Triangle>, face: Face, lo: Vertex, hi: Vertex, color_index: u8)
+{
+    let corners = face_corners(face, lo, hi);
+    let normal = face_normal(face);
+
+    triangles.push(Triangle {
+        normal,
+        a: corners[0],
+        b: corners[1],
+        c: corners[2],
+        color_index
+    });
+
+    triangles.push(Triangle {
+        normal,
+        a: corners[0],
+        b: corners[2],
+        c: corners[3],
+        color_index
+    });
+}
+
+/// Emits two triangles for every exposed face
+pub fn convert_triangles(mvoxels: &[Option<MetaVoxel>]) -> Vec<Triangle>
+{
+    let mut triangles = Vec::new();
+
+    for opt_mvoxel in mvoxels
+    {
+        match opt_mvoxel
+        {
+            Some(mvoxel) => {
+                let lo = Vertex {
+                    x: mvoxel.voxel.x as f32,
+                    y: mvoxel.voxel.y as f32,
+                    z: mvoxel.voxel.z as f32
+                };
+                let hi = Vertex {
+                    x: lo.x + 1.0,
+                    y: lo.y + 1.0,
+                    z: lo.z + 1.0
+                };
+
+                for face in FACES.iter()
+                {
+                    if mvoxel.has_face(*face)
+                    {
+                        push_face(&mut triangles, *face, lo, hi, mvoxel.voxel.i);
+                    }
+                }
+            },
+            None => {
+
+            }
+        }
+    }
+
+    return triangles;
+}
+
+/// Multiplies every position of `triangles` by `factor`
+pub fn scale_triangles(triangles: &mut [Triangle], factor: f32)
+{
+    for triangle in triangles
+    {
+        for vertex in [&mut triangle.a, &mut triangle.b, &mut triangle.c].iter_mut()
+        {
+            vertex.x *= factor;
+            vertex.y *= factor;
+            vertex.z *= factor;
+        }
+    }
+}
+
+/// Multiplies welded positions by `factor`. Exporters weld vertices in voxel
+/// units and only scale them afterwards, as the corners of small voxel sizes
+/// would otherwise fall within welding distance of each other and merge.
+pub fn scale_welded_positions(positions: &mut [Vertex], factor: f32)
+{
+    for position in positions
+    {
+        position.x *= factor;
+        position.y *= factor;
+        position.z *= factor;
+    }
+}
+
+/// Adds `offset` to every position of `triangles`
+pub fn translate_triangles(triangles: &mut [Triangle], offset: [f32; 3])
+{
+    for triangle in triangles
+    {
+        for vertex in [&mut triangle.a, &mut triangle.b, &mut triangle.c].iter_mut()
+        {
+            vertex.x += offset[0];
+            vertex.y += offset[1];
+            vertex.z += offset[2];
+        }
+    }
+}
+
+/// Deduplicates the vertices and normals of `triangles` into shared lists,
+/// appending to whatever `vertices` and `normals` already hold
+pub fn index_triangles(triangles: &[Triangle], vertices: &mut Vec<Vertex>, normals: &mut Vec<Normal>, idx_triangles: &mut Vec<IndexedTriangle>)
+{
+    let mut welder = Welder::from_lists(std::mem::take(vertices), std::mem::take(normals));
+    idx_triangles.reserve(triangles.len());
+
+    for triangle in triangles
+    {
+        idx_triangles.push(IndexedTriangle {
+            normal: triangle.normal,
+            normal_index: welder.normal(triangle.normal),
+            a: welder.vertex(triangle.a),
+            b: welder.vertex(triangle.b),
+            c: welder.vertex(triangle.c),
+            color_index: triangle.color_index
+        });
+    }
+
+    *vertices = welder.vertices;
+    *normals = welder.normals;
+}
+
+/// Runs the whole meshing pipeline on a single model, in voxel coordinates
+/// moved by the pivot of `options`
+pub fn convert_model(model: &dot_vox::Model, options: &MeshOptions) -> Result<Vec<Triangle>>
+{
+    let size = model.size;
+    let mut voxels = reorder_voxels(&model.voxels, &size)?;
+    if options.fill_cavities
+    {
+        fill_cavities(&mut voxels, &size);
+    }
+    match &options.hollow
+    {
+        Some(hollow) => {
+            crate::hollow::hollow_voxels(&mut voxels, &size, hollow);
+        },
+        None => {}
+    }
+    let mvoxels = convert_meta_voxels(&voxels, &size);
+    let mut triangles = match options.mesher
+    {
+        Mesher::Naive => convert_triangles(&mvoxels),
+        Mesher::Greedy { merge_colors } => crate::greedy::convert_greedy_triangles(&mvoxels, &size, merge_colors),
+        Mesher::Manifold => crate::manifold::convert_manifold_triangles(&mvoxels)
+    };
+
+    // Scene nodes and exports place the MagicaVoxel pivot, so the chosen one is moved there
+    if options.pivot != Pivot::MagicaVoxel
+    {
+        let from = options.pivot.position(model);
+        let to = Pivot::MagicaVoxel.position(model);
+        translate_triangles(&mut triangles, [to[0] - from[0], to[1] - from[1], to[2] - from[2]]);
+    }
+    return Ok(triangles);
+}
+
+/// Meshes every model placed by `scene`, returning the world space triangles
+/// of each instance. Models placed more than once are only meshed once.
+pub fn convert_instances(in_data: &dot_vox::DotVoxData, scene: &Scene, options: &MeshOptions) -> Result<Vec<(Instance, Vec<Triangle>)>>
+{
+    let mut meshes: HashMap<usize, Vec<Triangle>> = HashMap::new();
+    let mut instances: Vec<(Instance, Vec<Triangle>)> = Vec::new();
+    for instance in scene.instances(&in_data.models)
+    {
+        let triangles = match meshes.entry(instance.model)
+        {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(convert_model(&in_data.models[instance.model], options)?)
+        };
+        let placed = instance.transform.apply_triangles(triangles);
+        instances.push((instance, placed));
+    }
+
+    return Ok(instances);
+}
+
+/// Runs the meshing pipeline on every model placed by `scene` and merges the
+/// results into one mesh
+pub fn convert_vox(in_data: &dot_vox::DotVoxData, scene: &Scene, options: &MeshOptions) -> Result<Vec<Triangle>>
+{
+    let mut triangles: Vec<Triangle> = Vec::new();
+    for (_, mut placed) in convert_instances(in_data, scene, options)?
+    {
+        triangles.append(&mut placed);
+    }
+
+    return Ok(triangles);
+}
+
+/// Which palette indices a `ColorPart` holds
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorGroup
+{
+    /// The indices a color group mapping puts into the group with this number
+    Group(usize),
+    /// A single palette index the mapping leaves out
+    Index(u8)
+}
+
+/// The voxels of one group of palette indices, as a copy of the input holding nothing else
+pub struct ColorPart
+{
+    pub group: ColorGroup,
+    pub in_data: dot_vox::DotVoxData
+}
+
+/// Splits the voxels of the models placed by `scene` into groups of palette
+/// indices. `groups` maps palette indices to the number of the group they join,
+/// every other index forms a group of its own. Models keep their size,
+/// so the parts share the coordinate frame of `scene`, and as voxels of other
+/// groups are left out, each part meshes into closed bodies.
+pub fn split_by_color(in_data: &dot_vox::DotVoxData, scene: &Scene, groups: &BTreeMap<u8, usize>) -> Vec<ColorPart>
+{
+    let group_of = |index: u8| groups.get(&index).map(|group| ColorGroup::Group(*group)).unwrap_or(ColorGroup::Index(index));
+    let used: BTreeSet<ColorGroup> = scene.models().into_iter()
+        .filter_map(|model| in_data.models.get(model))
+        .flat_map(|model| model.voxels.iter().map(|voxel| group_of(voxel.i)))
+        .collect();
+
+    return used.into_iter()
+        .map(|group| ColorPart {
+            group,
+            in_data: dot_vox::DotVoxData {
+                version: in_data.version,
+                models: in_data.models.iter()
+                    .map(|model| dot_vox::Model {
+                        size: model.size,
+                        voxels: model.voxels.iter().filter(|voxel| group_of(voxel.i) == group).copied().collect()
+                    })
+                    .collect(),
+                palette: in_data.palette.clone(),
+                materials: in_data.materials.clone()
+            }
+        })
+        .collect();
+}
@@ -0,0 +1,228 @@
+//! Wavefront OBJ export with an accompanying MTL material library.
+
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{BufWriter, Write};
+
+use crate::color::Color;
+use crate::error::Result;
+use crate::mesh::{convert_instances, index_triangles, IndexedTriangle, MeshOptions, Normal, Vertex};
+use crate::palette::{material_name, palette_texture, PaletteTexture, TEXTURE_MATERIAL};
+use crate::scene::Scene;
+
+/// A polygon of an OBJ object, as zero based indices into the shared lists
+struct Polygon
+{
+    vertices: Vec<usize>,
+    normal_index: usize,
+    color_index: u8
+}
+
+/// The corners of `first` and `second` as one quad, if they share an edge, face
+/// the same way and have the same color. The quad keeps the winding of both.
+fn join_quad(first: &IndexedTriangle, second: &IndexedTriangle, vertices: &[Vertex]) -> Option<Vec<usize>>
+{
+    if second.normal_index != first.normal_index || second.color_index != first.color_index
+    {
+        return None;
+    }
+
+    let first_ring = [first.a, first.b, first.c];
+    let second_ring = [second.a, second.b, second.c];
+    for k in 0..3
+    {
+        // Triangles wound the same way run along their shared edge in opposite directions
+        let (start, end) = (first_ring[k], first_ring[(k + 1) % 3]);
+        for j in 0..3
+        {
+            if second_ring[j] == end && second_ring[(j + 1) % 3] == start
+            {
+                let quad = vec![end, first_ring[(k + 2) % 3], start, second_ring[(j + 2) % 3]];
+                if is_convex(&quad, first.normal, vertices)
+                {
+                    return Some(quad);
+                }
+                return None;
+            }
+        }
+    }
+
+    return None;
+}
+
+/// Whether every corner of `quad` turns the same way around `normal`
+fn is_convex(quad: &[usize], normal: Normal, vertices: &[Vertex]) -> bool
+{
+    return (0..quad.len()).all(|i| {
+        let (a, b, c) = (vertices[quad[i]], vertices[quad[(i + 1) % quad.len()]], vertices[quad[(i + 2) % quad.len()]]);
+        let u = [b.x - a.x, b.y - a.y, b.z - a.z];
+        let v = [c.x - b.x, c.y - b.y, c.z - b.z];
+        let cross = [u[1] * v[2] - u[2] * v[1], u[2] * v[0] - u[0] * v[2], u[0] * v[1] - u[1] * v[0]];
+        return cross[0] * normal.x + cross[1] * normal.y + cross[2] * normal.z > 0.0;
+    });
+}
+
+/// Joins the triangle pairs emitted for each face back into quads, whichever
+/// corner they start at, leaving any other triangle as is
+fn collect_polygons(idx_triangles: &[IndexedTriangle], vertices: &[Vertex]) -> Vec<Polygon>
+{
+    let mut polygons: Vec<Polygon> = Vec::with_capacity(idx_triangles.len() / 2);
+    let mut i = 0;
+    while i < idx_triangles.len()
+    {
+        let first = &idx_triangles[i];
+        match idx_triangles.get(i + 1).and_then(|second| join_quad(first, second, vertices))
+        {
+            Some(quad) => {
+                polygons.push(Polygon {
+                    vertices: quad,
+                    normal_index: first.normal_index,
+                    color_index: first.color_index
+                });
+                i += 2;
+            },
+            None => {
+                polygons.push(Polygon {
+                    vertices: vec![first.a, first.b, first.c],
+                    normal_index: first.normal_index,
+                    color_index: first.color_index
+                });
+                i += 1;
+            }
+        }
+    }
+
+    return polygons;
+}
+
+/// Writes one `newmtl` entry for every palette index in `used`
+fn write_mtl<M: Write>(palette: &[u32], used: &BTreeSet<u8>, mtl_writer: &mut M) -> Result<()>
+{
+    let mut mtl = BufWriter::new(mtl_writer);
+    writeln!(mtl, "# Voxport material library")?;
+    for index in used
+    {
+        let color = Color::from_palette(palette, *index);
+        writeln!(mtl)?;
+        writeln!(mtl, "newmtl {}", material_name(*index))?;
+        writeln!(mtl, "Ka 0 0 0")?;
+        writeln!(mtl, "Kd {:.6} {:.6} {:.6}", color.r as f32 / 255.0, color.g as f32 / 255.0, color.b as f32 / 255.0)?;
+        writeln!(mtl, "Ks 0 0 0")?;
+        writeln!(mtl, "d {:.6}", color.a as f32 / 255.0)?;
+        writeln!(mtl, "illum 1")?;
+    }
+
+    mtl.flush()?;
+    return Ok(());
+}
+
+/// Writes the single material showing `texture`
+fn write_texture_mtl<M: Write>(texture: &PaletteTexture, mtl_writer: &mut M) -> Result<()>
+{
+    let mut mtl = BufWriter::new(mtl_writer);
+    writeln!(mtl, "# Voxport material library")?;
+    writeln!(mtl)?;
+    writeln!(mtl, "newmtl {}", TEXTURE_MATERIAL)?;
+    writeln!(mtl, "Ka 0 0 0")?;
+    writeln!(mtl, "Kd 1 1 1")?;
+    writeln!(mtl, "Ks 0 0 0")?;
+    writeln!(mtl, "illum 1")?;
+    writeln!(mtl, "map_Kd {}", texture.uri)?;
+
+    mtl.flush()?;
+    return Ok(());
+}
+
+/// Meshes every model placed by `scene` and writes each instance as an object
+/// of a Wavefront OBJ file. The materials for every used palette index go to
+/// `mtl_writer`, and `mtl_uri` is the path of that library relative to the OBJ file.
+/// With a palette texture the faces get texture coordinates and a single material instead.
+pub fn convert_vox_obj<W: Write, M: Write>(in_data: &dot_vox::DotVoxData, scene: &Scene, options: &MeshOptions, writer: &mut W, mtl_writer: &mut M, mtl_uri: &str) -> Result<()>
+{
+    let mut obj = BufWriter::new(writer);
+    let mut vertices: Vec<Vertex> = Vec::new();
+    let mut normals: Vec<Normal> = Vec::new();
+    let mut used: BTreeSet<u8> = BTreeSet::new();
+    let texture = palette_texture(options)?;
+    // Vertices are welded in voxel units and scaled as written, see `scale_welded_positions`
+    let scale = options.position_scale();
+    // Zero based index of the texture coordinate of every palette index in use
+    let mut uvs: BTreeMap<u8, usize> = BTreeMap::new();
+
+    writeln!(obj, "# Voxport")?;
+    writeln!(obj, "mtllib {}", mtl_uri)?;
+
+    for (instance, triangles) in convert_instances(in_data, scene, options)?
+    {
+        let mut idx_triangles: Vec<IndexedTriangle> = Vec::new();
+        let first_vertex = vertices.len();
+        let first_normal = normals.len();
+        index_triangles(&triangles, &mut vertices, &mut normals, &mut idx_triangles);
+
+        let mut polygons = collect_polygons(&idx_triangles, &vertices);
+        polygons.sort_by_key(|polygon| polygon.color_index);
+
+        writeln!(obj)?;
+        writeln!(obj, "o {}", instance.name)?;
+        for vertex in &vertices[first_vertex..]
+        {
+            writeln!(obj, "v {} {} {}", vertex.x * scale, vertex.y * scale, vertex.z * scale)?;
+        }
+        for normal in &normals[first_normal..]
+        {
+            writeln!(obj, "vn {} {} {}", normal.x, normal.y, normal.z)?;
+        }
+
+        match texture
+        {
+            Some(texture) => {
+                for polygon in &polygons
+                {
+                    let next = uvs.len();
+                    if let Entry::Vacant(entry) = uvs.entry(polygon.color_index)
+                    {
+                        // OBJ counts v up from the bottom of the texture
+                        let uv = texture.layout.uv(polygon.color_index);
+                        writeln!(obj, "vt {} {}", uv[0], 1.0 - uv[1])?;
+                        entry.insert(next);
+                    }
+                }
+                if !polygons.is_empty()
+                {
+                    writeln!(obj, "usemtl {}", TEXTURE_MATERIAL)?;
+                }
+            },
+            None => {}
+        }
+
+        let mut current_color: Option<u8> = None;
+        for polygon in &polygons
+        {
+            if texture.is_none() && current_color != Some(polygon.color_index)
+            {
+                writeln!(obj, "usemtl {}", material_name(polygon.color_index))?;
+                current_color = Some(polygon.color_index);
+                used.insert(polygon.color_index);
+            }
+
+            // OBJ indices start at 1
+            let uv = uvs.get(&polygon.color_index).map(|uv| (uv + 1).to_string()).unwrap_or_default();
+            let corners = polygon.vertices.iter()
+                .map(|vertex| format!("{}/{}/{}", vertex + 1, uv, polygon.normal_index + 1))
+                .collect::<Vec<_>>();
+            writeln!(obj, "f {}", corners.join(" "))?;
+        }
+    }
+
+    obj.flush()?;
+    match texture
+    {
+        Some(texture) => {
+            write_texture_mtl(texture, mtl_writer)?;
+        },
+        None => {
+            write_mtl(&in_data.palette, &used, mtl_writer)?;
+        }
+    }
+    return Ok(());
+}
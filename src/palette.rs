@@ -0,0 +1,366 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::prelude::*;
+use std::io::LineWriter;
+
+use crate::color::Color;
+use crate::error::{Result, VoxportError};
+use crate::mesh::{MeshOptions, Mesher};
+
+/// Name of the material exporters use for faces of palette index `index`
+pub fn material_name(index: u8) -> String
+{
+    return format!("palette_{}", index);
+}
+
+/// Name of the material exporters use for faces mapped onto a palette texture
+pub const TEXTURE_MATERIAL: &str = "palette";
+
+/// Pixel width and height of every color of a swatch strip
+pub const SWATCH_SIZE: u32 = 16;
+
+/// File formats palettes are exported in
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PaletteFormat
+{
+    /// JASC-PAL text, as read by Paint Shop Pro and Aseprite
+    Jasc,
+    /// GIMP `.gpl` text
+    Gimp,
+    /// Adobe `.act` binary color table
+    Act,
+    /// One `rrggbb` hex value per line, as in Lospec `.hex` files
+    Hex,
+    /// A PNG strip with a square swatch per color
+    Png
+}
+
+/// Palette indices used by the voxels of any model of `in_data`
+pub fn used_palette_indices(in_data: &dot_vox::DotVoxData) -> BTreeSet<u8>
+{
+    return in_data.models.iter()
+        .flat_map(|model| model.voxels.iter().map(|voxel| voxel.i))
+        .collect();
+}
+
+/// Writes the palette of `in_data` in `format`. With `used_only` only the
+/// colors used by voxels are written, in palette order.
+pub fn export_palette<W: Write>(in_data: &dot_vox::DotVoxData, format: PaletteFormat, used_only: bool, writer: &mut W) -> Result<()>
+{
+    let colors: Vec<(u8, Color)> = if used_only
+    {
+        used_palette_indices(in_data).into_iter().map(|index| (index, Color::from_palette(&in_data.palette, index))).collect()
+    } else {
+        in_data.palette.iter().take(256).enumerate().map(|(index, entry)| (index as u8, (*entry).into())).collect()
+    };
+
+    match format
+    {
+        PaletteFormat::Jasc => {
+            let mut file = LineWriter::new(writer);
+            file.write_all(b"JASC-PAL\n")?;
+            file.write_all(b"0100\n")?;
+            writeln!(file, "{}", colors.len())?;
+            for (_, color) in colors
+            {
+                let color_str: String = color.into();
+                file.write_all((color_str + "\n").as_bytes())?;
+            }
+        },
+        PaletteFormat::Gimp => {
+            let mut file = LineWriter::new(writer);
+            writeln!(file, "GIMP Palette")?;
+            writeln!(file, "Name: Voxport")?;
+            writeln!(file, "Columns: 16")?;
+            writeln!(file, "#")?;
+            for (index, color) in colors
+            {
+                writeln!(file, "{:3} {:3} {:3}\t{}", color.r, color.g, color.b, material_name(index))?;
+            }
+        },
+        PaletteFormat::Act => {
+            // 256 RGB triples, followed by the number of colors in use and no transparent index
+            let mut table = vec![0u8; 256 * 3];
+            for (slot, (_, color)) in colors.iter().enumerate()
+            {
+                table[slot * 3..slot * 3 + 3].copy_from_slice(&[color.r, color.g, color.b]);
+            }
+            writer.write_all(&table)?;
+            if colors.len() < 256
+            {
+                writer.write_all(&(colors.len() as u16).to_be_bytes())?;
+                writer.write_all(&u16::MAX.to_be_bytes())?;
+            }
+        },
+        PaletteFormat::Hex => {
+            let mut file = LineWriter::new(writer);
+            for (_, color) in colors
+            {
+                writeln!(file, "{:02x}{:02x}{:02x}", color.r, color.g, color.b)?;
+            }
+        },
+        PaletteFormat::Png => {
+            if colors.is_empty()
+            {
+                return Err(VoxportError::Export("a swatch strip needs at least one color".to_string()));
+            }
+            let width = colors.len() as u32 * SWATCH_SIZE;
+            let mut pixels: Vec<u8> = Vec::with_capacity((width * SWATCH_SIZE * 4) as usize);
+            for _ in 0..SWATCH_SIZE
+            {
+                for (_, color) in &colors
+                {
+                    for _ in 0..SWATCH_SIZE
+                    {
+                        pixels.extend_from_slice(&[color.r, color.g, color.b, color.a]);
+                    }
+                }
+            }
+
+            let mut encoder = png::Encoder::new(writer, width, SWATCH_SIZE);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.write_header()?.write_image_data(&pixels)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses the `r g b` triple at the start of a line of a text palette
+fn parse_rgb(line: &str) -> Option<Color>
+{
+    let mut channels = line.split_whitespace().map(|channel| channel.parse::<u8>());
+    match (channels.next(), channels.next(), channels.next())
+    {
+        (Some(Ok(r)), Some(Ok(g)), Some(Ok(b))) => {
+            return Some(Color { r, g, b, a: 255 });
+        },
+        _ => {
+            return None;
+        }
+    }
+}
+
+fn parse_text_palette(format: PaletteFormat, text: &str) -> Result<Vec<Color>>
+{
+    let invalid = |line: usize, reason: &str| VoxportError::InvalidInput(format!("line {}: {}", line + 1, reason));
+    let mut lines = text.lines().map(|line| line.trim()).enumerate();
+    let mut colors: Vec<Color> = Vec::new();
+    match format
+    {
+        PaletteFormat::Jasc => {
+            if lines.next().map(|(_, line)| line) != Some("JASC-PAL")
+            {
+                return Err(invalid(0, "not a JASC-PAL file"));
+            }
+            lines.next();
+            let count: usize = match lines.next()
+            {
+                Some((number, line)) => line.parse().map_err(|_| invalid(number, "expected the number of colors"))?,
+                None => 0
+            };
+            for (number, line) in lines.filter(|(_, line)| !line.is_empty())
+            {
+                colors.push(parse_rgb(line).ok_or_else(|| invalid(number, "expected a color like \"255 128 0\""))?);
+            }
+            if colors.len() != count
+            {
+                return Err(invalid(2, &format!("announces {} colors but lists {}", count, colors.len())));
+            }
+        },
+        PaletteFormat::Gimp => {
+            if lines.next().map(|(_, line)| line) != Some("GIMP Palette")
+            {
+                return Err(invalid(0, "not a GIMP palette"));
+            }
+            for (number, line) in lines
+            {
+                if line.is_empty() || line.starts_with('#') || line.starts_with("Name:") || line.starts_with("Columns:")
+                {
+                    continue;
+                }
+                colors.push(parse_rgb(line).ok_or_else(|| invalid(number, "expected a color like \"255 128 0\""))?);
+            }
+        },
+        _ => {
+            return Err(VoxportError::InvalidInput(format!("{:?} palettes can't be read", format)));
+        }
+    }
+    return Ok(colors);
+}
+
+/// Whether `pixels`, `width` wide, is a strip of square swatches as written by
+/// `export_palette`: `SWATCH_SIZE` pixels high, with every swatch all one color
+fn is_swatch_strip(pixels: &[Color], width: usize) -> bool
+{
+    let size = SWATCH_SIZE as usize;
+    if pixels.len() != width * size || !width.is_multiple_of(size)
+    {
+        return false;
+    }
+    return pixels.iter().enumerate().all(|(i, pixel)| *pixel == pixels[i % width / size * size]);
+}
+
+/// Reads a swatch strip as written by `export_palette` one color per swatch,
+/// and any other PNG one color per pixel, row by row as written by `export_palette_texture`
+fn parse_png_palette<R: Read>(reader: R) -> Result<Vec<Color>>
+{
+    let invalid = |err: png::DecodingError| VoxportError::InvalidInput(err.to_string());
+    let mut decoder = png::Decoder::new(reader);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(invalid)?;
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).map_err(invalid)?;
+    pixels.truncate(info.buffer_size());
+
+    let colors: Vec<Color> = match info.color_type
+    {
+        png::ColorType::Grayscale => pixels.iter().map(|l| Color { r: *l, g: *l, b: *l, a: 255 }).collect(),
+        png::ColorType::GrayscaleAlpha => pixels.chunks(2).map(|p| Color { r: p[0], g: p[0], b: p[0], a: p[1] }).collect(),
+        png::ColorType::Rgb => pixels.chunks(3).map(|p| Color { r: p[0], g: p[1], b: p[2], a: 255 }).collect(),
+        _ => pixels.chunks(4).map(|p| Color { r: p[0], g: p[1], b: p[2], a: p[3] }).collect()
+    };
+
+    let width = info.width as usize;
+    if is_swatch_strip(&colors, width)
+    {
+        return Ok(colors[..width].iter().step_by(SWATCH_SIZE as usize).copied().collect());
+    }
+    return Ok(colors);
+}
+
+/// Reads a palette in `format`, which can be JASC-PAL, GIMP or a PNG, either
+/// a swatch strip or an image with one color per pixel, read row by row
+pub fn import_palette<R: Read>(format: PaletteFormat, mut reader: R) -> Result<Vec<Color>>
+{
+    match format
+    {
+        PaletteFormat::Png => {
+            return parse_png_palette(reader);
+        },
+        _ => {
+            let mut text = String::new();
+            reader.read_to_string(&mut text)?;
+            return parse_text_palette(format, &text);
+        }
+    }
+}
+
+/// Replaces the first entries of the palette of `in_data` with `colors`,
+/// keeping the rest. Entry `k` becomes the color of voxels with `Voxel.i == k`.
+pub fn apply_palette(in_data: &mut dot_vox::DotVoxData, colors: &[Color])
+{
+    if in_data.palette.len() < 256
+    {
+        in_data.palette.resize(256, 0xFF_FF_FF_FF);
+    }
+    for (entry, color) in in_data.palette.iter_mut().zip(colors)
+    {
+        *entry = (*color).into();
+    }
+}
+
+/// Moves every voxel with a palette index in `remap` to the index it maps to,
+/// taking its color and material
+pub fn remap_palette_indices(in_data: &mut dot_vox::DotVoxData, remap: &BTreeMap<u8, u8>)
+{
+    for voxel in in_data.models.iter_mut().flat_map(|model| model.voxels.iter_mut())
+    {
+        match remap.get(&voxel.i)
+        {
+            Some(index) => {
+                voxel.i = *index;
+            },
+            None => {}
+        }
+    }
+}
+
+/// Writes the palette of `in_data` as a JASC-PAL text file
+pub fn export_jasc_palette<W: Write>(in_data: &dot_vox::DotVoxData, writer: &mut W) -> Result<()>
+{
+    return export_palette(in_data, PaletteFormat::Jasc, false, writer);
+}
+
+/// Arrangement of the 256 palette entries in a palette texture
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TextureLayout
+{
+    /// A 256x1 strip with entry `i` in column `i`
+    Strip,
+    /// A 16x16 grid filled row by row from the top left
+    Grid
+}
+
+impl TextureLayout
+{
+    /// Width and height of the texture in texels
+    pub fn size(self) -> (u32, u32)
+    {
+        match self
+        {
+            TextureLayout::Strip => (256, 1),
+            TextureLayout::Grid => (16, 16)
+        }
+    }
+
+    /// Column and row of the texel holding palette index `index`
+    pub fn texel(self, index: u8) -> (u32, u32)
+    {
+        let (width, _) = self.size();
+        return (index as u32 % width, index as u32 / width);
+    }
+
+    /// Texture coordinates of the center of the texel of palette index `index`,
+    /// with `v` running down from the top row as in glTF. OBJ and COLLADA count
+    /// `v` up from the bottom row and use `1 - v`.
+    pub fn uv(self, index: u8) -> [f32; 2]
+    {
+        let (width, height) = self.size();
+        let (column, row) = self.texel(index);
+        return [(column as f32 + 0.5) / width as f32, (row as f32 + 0.5) / height as f32];
+    }
+}
+
+/// A palette texture for exporters to map faces onto instead of giving every
+/// palette index a material of its own
+#[derive(Clone, Debug, PartialEq)]
+pub struct PaletteTexture
+{
+    pub layout: TextureLayout,
+    /// Path of the PNG written by `export_palette_texture`, relative to the exported file
+    pub uri: String
+}
+
+/// Returns the palette texture of `options`. Faces are mapped onto a single
+/// texel, so faces merged across palette indices can't be textured.
+pub(crate) fn palette_texture(options: &MeshOptions) -> Result<Option<&PaletteTexture>>
+{
+    match (&options.texture, options.mesher)
+    {
+        (Some(_), Mesher::Greedy { merge_colors: true }) => {
+            return Err(VoxportError::Export("faces merged across palette indices can't be mapped onto the palette texture".to_string()));
+        },
+        (texture, _) => {
+            return Ok(texture.as_ref());
+        }
+    }
+}
+
+/// Writes the palette of `in_data` as an RGBA PNG laid out as `layout`
+pub fn export_palette_texture<W: Write>(in_data: &dot_vox::DotVoxData, layout: TextureLayout, writer: &mut W) -> Result<()>
+{
+    let (width, height) = layout.size();
+    let mut pixels: Vec<u8> = Vec::with_capacity((width * height * 4) as usize);
+    for index in 0..=255u8
+    {
+        let color = Color::from_palette(&in_data.palette, index);
+        pixels.extend_from_slice(&[color.r, color.g, color.b, color.a]);
+    }
+
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&pixels)?;
+    return Ok(());
+}
@@ -0,0 +1,121 @@
+//! Stanford PLY export with per-vertex colors, in ASCII or binary little endian.
+
+use std::collections::HashMap;
+use std::io::{BufWriter, Write};
+
+use crate::color::Color;
+use crate::error::{Result, VoxportError};
+use crate::mesh::{convert_vox, MeshOptions, Triangle, Vertex};
+use crate::scene::Scene;
+use crate::weld::{lattice_key, LatticeKey};
+
+/// Encoding of the PLY body
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PlyFormat
+{
+    Ascii,
+    BinaryLittleEndian
+}
+
+impl PlyFormat
+{
+    fn header_name(self) -> &'static str
+    {
+        match self
+        {
+            Self::Ascii => "ascii",
+            Self::BinaryLittleEndian => "binary_little_endian"
+        }
+    }
+}
+
+/// Welds the corners of `triangles` into vertices that share both position and
+/// color, returning the vertices and the corner indices of every triangle
+fn index_colored_vertices(triangles: &[Triangle], palette: &[u32]) -> (Vec<(Vertex, Color)>, Vec<[usize; 3]>)
+{
+    let mut vertices: Vec<(Vertex, Color)> = Vec::new();
+    let mut vertex_indices: HashMap<(LatticeKey, u8), usize> = HashMap::new();
+    let mut faces: Vec<[usize; 3]> = Vec::with_capacity(triangles.len());
+
+    for triangle in triangles
+    {
+        let color = Color::from_palette(palette, triangle.color_index);
+        let mut face = [0usize; 3];
+        for (corner, vertex) in face.iter_mut().zip([triangle.a, triangle.b, triangle.c].iter())
+        {
+            *corner = *vertex_indices.entry((lattice_key(vertex.x, vertex.y, vertex.z), triangle.color_index)).or_insert_with(|| {
+                vertices.push((*vertex, color));
+                vertices.len() - 1
+            });
+        }
+        faces.push(face);
+    }
+
+    return (vertices, faces);
+}
+
+/// Meshes every model placed by `scene` and writes them as a single PLY mesh
+/// whose vertices carry `uchar` red, green, blue and alpha properties
+pub fn convert_vox_ply<W: Write>(in_data: &dot_vox::DotVoxData, scene: &Scene, options: &MeshOptions, format: PlyFormat, writer: &mut W) -> Result<()>
+{
+    let triangles = convert_vox(in_data, scene, options)?;
+    let (vertices, faces) = index_colored_vertices(&triangles, &in_data.palette);
+    // Applied to the vertices as they are written, once welded
+    let scale = options.position_scale();
+    if vertices.len() > i32::MAX as usize
+    {
+        return Err(VoxportError::Export("too many vertices for PLY int indices".to_string()));
+    }
+
+    let mut ply = BufWriter::new(writer);
+    writeln!(ply, "ply")?;
+    writeln!(ply, "format {} 1.0", format.header_name())?;
+    writeln!(ply, "comment Voxport")?;
+    writeln!(ply, "element vertex {}", vertices.len())?;
+    writeln!(ply, "property float x")?;
+    writeln!(ply, "property float y")?;
+    writeln!(ply, "property float z")?;
+    writeln!(ply, "property uchar red")?;
+    writeln!(ply, "property uchar green")?;
+    writeln!(ply, "property uchar blue")?;
+    writeln!(ply, "property uchar alpha")?;
+    writeln!(ply, "element face {}", faces.len())?;
+    writeln!(ply, "property list uchar int vertex_indices")?;
+    writeln!(ply, "end_header")?;
+
+    match format
+    {
+        PlyFormat::Ascii => {
+            for (vertex, color) in &vertices
+            {
+                writeln!(ply, "{} {} {} {} {} {} {}", vertex.x * scale, vertex.y * scale, vertex.z * scale, color.r, color.g, color.b, color.a)?;
+            }
+
+            for face in &faces
+            {
+                writeln!(ply, "3 {} {} {}", face[0], face[1], face[2])?;
+            }
+        },
+        PlyFormat::BinaryLittleEndian => {
+            for (vertex, color) in &vertices
+            {
+                ply.write_all(&(vertex.x * scale).to_le_bytes())?;
+                ply.write_all(&(vertex.y * scale).to_le_bytes())?;
+                ply.write_all(&(vertex.z * scale).to_le_bytes())?;
+                ply.write_all(&[color.r, color.g, color.b, color.a])?;
+            }
+
+            for face in &faces
+            {
+                ply.write_all(&[3])?;
+                for corner in face
+                {
+                    ply.write_all(&(*corner as i32).to_le_bytes())?;
+                }
+            }
+        }
+    }
+
+    ply.flush()?;
+    return Ok(());
+}
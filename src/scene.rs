@@ -0,0 +1,908 @@
+//! The MagicaVoxel scene graph, which places models in the world through
+//! transform (`nTRN`), group (`nGRP`) and shape (`nSHP`) nodes.
+//!
+//! `dot_vox` skips these chunks, so they are read here from the raw file.
+
+use std::collections::{BTreeSet, HashMap};
+use std::convert::TryInto;
+use std::ops::RangeInclusive;
+
+use crate::error::{Result, VoxportError};
+use crate::mesh::{Normal, Triangle, Vertex};
+
+/// A rotation by a signed permutation matrix followed by a translation, which
+/// is all a MagicaVoxel transform node can express
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transform
+{
+    /// Row major, applied to column vectors
+    pub rotation: [[i32; 3]; 3],
+    pub translation: [i32; 3]
+}
+
+impl Default for Transform
+{
+    fn default() -> Self
+    {
+        Self::IDENTITY
+    }
+}
+
+impl Transform
+{
+    pub const IDENTITY: Transform = Transform {
+        rotation: [[1, 0, 0], [0, 1, 0], [0, 0, 1]],
+        translation: [0, 0, 0]
+    };
+
+    pub fn from_translation(translation: [i32; 3]) -> Transform
+    {
+        return Transform {
+            translation,
+            ..Transform::IDENTITY
+        };
+    }
+
+    /// Unpacks the `_r` byte of a transform frame. Bits 0-1 and 2-3 hold the
+    /// column of the non-zero entry of the first and second rows, and bits 4-6
+    /// the signs of the three rows.
+    pub fn from_packed(rotation: u8, translation: [i32; 3]) -> Result<Transform>
+    {
+        let first = (rotation & 3) as usize;
+        let second = ((rotation >> 2) & 3) as usize;
+        if first > 2 || second > 2 || first == second
+        {
+            return Err(VoxportError::Parse(format!("invalid packed rotation {}", rotation)));
+        }
+        let third = 3 - first - second;
+
+        // MagicaVoxel applies the matrix to row vectors, so it is stored
+        // transposed here to be applied to column vectors
+        let mut matrix = [[0; 3]; 3];
+        for (row, column) in [first, second, third].iter().enumerate()
+        {
+            matrix[*column][row] = if rotation & (1 << (4 + row)) != 0 { -1 } else { 1 };
+        }
+
+        return Ok(Transform {
+            rotation: matrix,
+            translation
+        });
+    }
+
+    /// Returns the transform applying `other` first and then `self`
+    pub fn then(&self, other: &Transform) -> Transform
+    {
+        let mut rotation = [[0; 3]; 3];
+        let mut translation = self.translation;
+        for (row, values) in rotation.iter_mut().enumerate()
+        {
+            for (column, value) in values.iter_mut().enumerate()
+            {
+                *value = (0..3).map(|k| self.rotation[row][k] * other.rotation[k][column]).sum();
+                translation[row] += self.rotation[row][column] * other.translation[column];
+            }
+        }
+
+        return Transform {
+            rotation,
+            translation
+        };
+    }
+
+    /// Whether the transform mirrors, turning the winding of triangles inside out
+    pub fn is_mirror(&self) -> bool
+    {
+        let r = &self.rotation;
+        let determinant = r[0][0] * (r[1][1] * r[2][2] - r[1][2] * r[2][1]) -
+                          r[0][1] * (r[1][0] * r[2][2] - r[1][2] * r[2][0]) +
+                          r[0][2] * (r[1][0] * r[2][1] - r[1][1] * r[2][0]);
+        return determinant < 0;
+    }
+
+    fn rotate(&self, v: [f32; 3]) -> [f32; 3]
+    {
+        let mut out = [0.0; 3];
+        for (row, value) in out.iter_mut().enumerate()
+        {
+            *value = (0..3).map(|k| self.rotation[row][k] as f32 * v[k]).sum();
+        }
+        return out;
+    }
+
+    pub fn apply_vertex(&self, vertex: Vertex) -> Vertex
+    {
+        let v = self.rotate([vertex.x, vertex.y, vertex.z]);
+        return Vertex {
+            x: v[0] + self.translation[0] as f32,
+            y: v[1] + self.translation[1] as f32,
+            z: v[2] + self.translation[2] as f32
+        };
+    }
+
+    pub fn apply_normal(&self, normal: Normal) -> Normal
+    {
+        let n = self.rotate([normal.x, normal.y, normal.z]);
+        return Normal {
+            x: n[0],
+            y: n[1],
+            z: n[2]
+        };
+    }
+
+    /// Transforms `triangles`, swapping corners where needed to keep them
+    /// counter-clockwise when seen from outside
+    pub fn apply_triangles(&self, triangles: &[Triangle]) -> Vec<Triangle>
+    {
+        let mirror = self.is_mirror();
+        return triangles.iter().map(|triangle| {
+            let (b, c) = if mirror { (triangle.c, triangle.b) } else { (triangle.b, triangle.c) };
+            Triangle {
+                normal: self.apply_normal(triangle.normal),
+                a: self.apply_vertex(triangle.a),
+                b: self.apply_vertex(b),
+                c: self.apply_vertex(c),
+                color_index: triangle.color_index
+            }
+        }).collect();
+    }
+
+    /// The transform as a 4x4 row major matrix
+    pub fn to_matrix(&self) -> [f32; 16]
+    {
+        return self.to_scaled_matrix(1.0);
+    }
+
+    /// The transform as a 4x4 row major matrix, with the translation scaled
+    /// from voxels to other units by `scale`
+    pub fn to_scaled_matrix(&self, scale: f32) -> [f32; 16]
+    {
+        let r = &self.rotation;
+        let t = &self.translation;
+        return [
+            r[0][0] as f32, r[0][1] as f32, r[0][2] as f32, t[0] as f32 * scale,
+            r[1][0] as f32, r[1][1] as f32, r[1][2] as f32, t[1] as f32 * scale,
+            r[2][0] as f32, r[2][1] as f32, r[2][2] as f32, t[2] as f32 * scale,
+            0.0, 0.0, 0.0, 1.0
+        ];
+    }
+}
+
+/// Moves the origin of a model to its center, where MagicaVoxel puts it
+pub fn model_pivot(size: &dot_vox::Size) -> Transform
+{
+    return Transform::from_translation([
+        -((size.x / 2) as i32),
+        -((size.y / 2) as i32),
+        -((size.z / 2) as i32)
+    ]);
+}
+
+/// Point of a model that its scene node places, and that meshes of a single
+/// model are exported around
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Pivot
+{
+    /// The center of the model size, rounded down to whole voxels as MagicaVoxel does
+    MagicaVoxel,
+    /// The corner of the model size at voxel coordinates 0, 0, 0
+    Corner,
+    /// The center of the box around the voxels of the model
+    Center,
+    /// The center of the bottom of the box around the voxels of the model
+    BottomCenter,
+    /// A point given in the voxel coordinates of the model
+    Point([f32; 3])
+}
+
+impl Default for Pivot
+{
+    fn default() -> Self
+    {
+        return Pivot::MagicaVoxel;
+    }
+}
+
+impl Pivot
+{
+    /// Position of the pivot in the voxel coordinates of `model`. The box around
+    /// the voxels of a model without any is its size.
+    pub fn position(&self, model: &dot_vox::Model) -> [f32; 3]
+    {
+        let size = [model.size.x as f32, model.size.y as f32, model.size.z as f32];
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+        for voxel in &model.voxels
+        {
+            for (axis, value) in [voxel.x, voxel.y, voxel.z].iter().enumerate()
+            {
+                min[axis] = min[axis].min(*value as f32);
+                max[axis] = max[axis].max(*value as f32 + 1.0);
+            }
+        }
+        if model.voxels.is_empty()
+        {
+            min = [0.0; 3];
+            max = size;
+        }
+        let center = |axis: usize| (min[axis] + max[axis]) / 2.0;
+
+        match self
+        {
+            Pivot::MagicaVoxel => {
+                let translation = model_pivot(&model.size).translation;
+                return [-translation[0] as f32, -translation[1] as f32, -translation[2] as f32];
+            },
+            Pivot::Corner => {
+                return [0.0; 3];
+            },
+            Pivot::Center => {
+                return [center(0), center(1), center(2)];
+            },
+            Pivot::BottomCenter => {
+                return [center(0), center(1), min[2]];
+            },
+            Pivot::Point(point) => {
+                return *point;
+            }
+        }
+    }
+}
+
+/// A layer of the scene, as set up in MagicaVoxel's world editor
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layer
+{
+    pub id: u32,
+    pub name: Option<String>,
+    pub hidden: bool
+}
+
+/// A transform node together with the group or shape below it
+#[derive(Clone, Debug, PartialEq)]
+pub struct SceneNode
+{
+    /// Id of the transform node in the file
+    pub id: u32,
+    pub name: Option<String>,
+    pub hidden: bool,
+    pub layer: Option<u32>,
+    pub transform: Transform,
+    /// Index into `DotVoxData::models` of the shape below the transform
+    pub model: Option<usize>,
+    /// Models an animated shape shows in turn, one per frame and starting with
+    /// `model`. Shapes that aren't animated only list `model`.
+    pub frames: Vec<usize>,
+    pub children: Vec<SceneNode>
+}
+
+impl SceneNode
+{
+    /// The node name, falling back to a name made up from its model or id
+    pub fn label(&self) -> String
+    {
+        match (&self.name, self.model)
+        {
+            (Some(name), _) => name.clone(),
+            (None, Some(model)) => format!("Voxel-{}", model),
+            (None, None) => format!("Node-{}", self.id)
+        }
+    }
+}
+
+/// A model placed in the world by a shape node
+#[derive(Clone, Debug, PartialEq)]
+pub struct Instance
+{
+    pub model: usize,
+    pub name: String,
+    pub layer: Option<u32>,
+    pub hidden: bool,
+    /// Takes the model's voxel coordinates to world coordinates
+    pub transform: Transform
+}
+
+/// Which nodes of a scene to export. Empty lists select everything.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Selection
+{
+    /// Ranges of indices into `DotVoxData::models`
+    pub models: Vec<RangeInclusive<usize>>,
+    /// Layers, by name or id
+    pub layers: Vec<String>,
+    /// Names of nodes to export along with everything below them
+    pub nodes: Vec<String>,
+    /// Keeps hidden nodes and nodes on hidden layers, which are left out otherwise
+    pub include_hidden: bool
+}
+
+/// How a scene is split into parts that are exported to files of their own
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SplitMode
+{
+    /// One part per model, placed at the origin
+    Model,
+    /// One part per layer, in world space
+    Layer,
+    /// One part per named node and everything below it, in world space
+    Node
+}
+
+/// A part of a split scene
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScenePart
+{
+    pub name: String,
+    pub scene: Scene
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scene
+{
+    pub root: SceneNode,
+    pub layers: Vec<Layer>
+}
+
+impl Scene
+{
+    /// A scene with the pivot of every model at the origin, for files without a
+    /// scene graph. With the default pivot that is the MagicaVoxel center of the
+    /// model rather than its corner.
+    pub fn from_models(in_data: &dot_vox::DotVoxData) -> Scene
+    {
+        let children = (0..in_data.models.len()).map(|model| SceneNode {
+            id: model as u32 + 1,
+            name: None,
+            hidden: false,
+            layer: None,
+            transform: Transform::IDENTITY,
+            model: Some(model),
+            frames: vec![model],
+            children: Vec::new()
+        }).collect();
+
+        return Scene {
+            root: SceneNode {
+                id: 0,
+                name: None,
+                hidden: false,
+                layer: None,
+                transform: Transform::IDENTITY,
+                model: None,
+                frames: Vec::new(),
+                children
+            },
+            layers: Vec::new()
+        };
+    }
+
+    /// Reads the scene graph of the raw MagicaVoxel file `bytes`, which `in_data`
+    /// was parsed from
+    pub fn read(bytes: &[u8], in_data: &dot_vox::DotVoxData) -> Result<Scene>
+    {
+        let mut nodes: HashMap<u32, RawNode> = HashMap::new();
+        let mut layers: Vec<Layer> = Vec::new();
+
+        for (id, content) in read_chunks(bytes)?
+        {
+            let mut reader = Reader {
+                bytes: content,
+                offset: 0
+            };
+            match id
+            {
+                b"nTRN" => {
+                    let node_id = reader.u32()?;
+                    let attributes = reader.dict()?;
+                    let child = reader.u32()?;
+                    reader.i32()?; // Reserved
+                    let layer = reader.i32()?;
+                    let frame_count = reader.u32()?;
+                    let frame = if frame_count > 0 { reader.dict()? } else { HashMap::new() };
+                    nodes.insert(node_id, RawNode::Transform { attributes, child, layer, frame });
+                },
+                b"nGRP" => {
+                    let node_id = reader.u32()?;
+                    reader.dict()?;
+                    let child_count = reader.u32()?;
+                    let mut children: Vec<u32> = Vec::new();
+                    for _ in 0..child_count
+                    {
+                        children.push(reader.u32()?);
+                    }
+                    nodes.insert(node_id, RawNode::Group { children });
+                },
+                b"nSHP" => {
+                    let node_id = reader.u32()?;
+                    reader.dict()?;
+                    let model_count = reader.u32()?;
+                    if model_count == 0
+                    {
+                        return Err(VoxportError::Parse(format!("shape node {} has no model", node_id)));
+                    }
+                    // Further models are animation frames, ordered by their `_f` frame index
+                    let mut frames: Vec<(u32, usize)> = Vec::new();
+                    for i in 0..model_count
+                    {
+                        let model = reader.u32()? as usize;
+                        let attributes = reader.dict()?;
+                        if model >= in_data.models.len()
+                        {
+                            return Err(VoxportError::Parse(format!("shape node {} references missing model {}", node_id, model)));
+                        }
+                        let frame = attributes.get("_f").and_then(|frame| frame.trim().parse().ok()).unwrap_or(i);
+                        frames.push((frame, model));
+                    }
+                    frames.sort_by_key(|(frame, _)| *frame);
+                    nodes.insert(node_id, RawNode::Shape { models: frames.into_iter().map(|(_, model)| model).collect() });
+                },
+                b"LAYR" => {
+                    let layer_id = reader.u32()?;
+                    let attributes = reader.dict()?;
+                    layers.push(Layer {
+                        id: layer_id,
+                        name: attributes.get("_name").cloned(),
+                        hidden: attributes.get("_hidden").is_some_and(|hidden| hidden == "1")
+                    });
+                },
+                _ => {}
+            }
+        }
+
+        if nodes.is_empty()
+        {
+            let mut scene = Scene::from_models(in_data);
+            scene.layers = layers;
+            return Ok(scene);
+        }
+
+        return Ok(Scene {
+            root: build_node(&nodes, 0, 0)?,
+            layers
+        });
+    }
+
+    /// Indices of the models placed by the scene
+    pub fn models(&self) -> BTreeSet<usize>
+    {
+        let mut models: BTreeSet<usize> = BTreeSet::new();
+        collect_models(&self.root, &mut models);
+        return models;
+    }
+
+    /// Returns the scene with only the nodes `keep` accepts, along with
+    /// everything below them and the groups leading up to them
+    pub fn filter<F: Fn(&SceneNode) -> bool>(&self, keep: F) -> Option<Scene>
+    {
+        return prune(&self.root, &keep).map(|root| Scene {
+            root,
+            layers: self.layers.clone()
+        });
+    }
+
+    /// Returns the scene with only the nodes `selection` picks. The root is kept
+    /// even when nothing else is, leaving a scene that places no models.
+    pub fn select(&self, selection: &Selection) -> Scene
+    {
+        let named = if selection.nodes.is_empty()
+        {
+            Some(self.root.clone())
+        } else {
+            prune(&self.root, &|node: &SceneNode| node.name.as_ref().is_some_and(|name| selection.nodes.contains(name)))
+        };
+
+        let root = named.and_then(|root| select_node(&root, selection, &self.layers)).unwrap_or_else(|| SceneNode {
+            model: None,
+            frames: Vec::new(),
+            children: Vec::new(),
+            ..self.root.clone()
+        });
+        return Scene {
+            root,
+            layers: self.layers.clone()
+        };
+    }
+
+    /// Splits the scene into the frames of an animation. Animated shapes show
+    /// one model after the other, and when there are none every model the scene
+    /// places becomes a frame of its own, as in files from before the scene graph.
+    pub fn frames(&self) -> Vec<Scene>
+    {
+        let count = frame_count(&self.root);
+        if count > 1
+        {
+            return (0..count).map(|frame| Scene {
+                root: frame_node(&self.root, frame),
+                layers: self.layers.clone()
+            }).collect();
+        }
+
+        return self.models().into_iter()
+            .filter_map(|model| self.filter(|node| node.model == Some(model)))
+            .collect();
+    }
+
+    /// Splits the scene into the parts `mode` asks for, leaving out empty ones
+    pub fn split(&self, mode: SplitMode) -> Vec<ScenePart>
+    {
+        let mut parts: Vec<ScenePart> = Vec::new();
+        match mode
+        {
+            SplitMode::Model => {
+                // Models are named after the first named shape placing them
+                let mut named: Vec<&SceneNode> = Vec::new();
+                collect_named(&self.root, &mut named);
+                for model in self.models()
+                {
+                    let name = named.iter().find(|node| node.model == Some(model))
+                        .map(|node| node.label())
+                        .unwrap_or_else(|| format!("Voxel-{}", model));
+                    parts.push(ScenePart {
+                        name: name.clone(),
+                        scene: Scene {
+                            root: SceneNode {
+                                id: 0,
+                                name: Some(name),
+                                hidden: false,
+                                layer: None,
+                                transform: Transform::IDENTITY,
+                                model: Some(model),
+                                frames: vec![model],
+                                children: Vec::new()
+                            },
+                            layers: self.layers.clone()
+                        }
+                    });
+                }
+            },
+            SplitMode::Layer => {
+                for layer in &self.layers
+                {
+                    match self.filter(|node| node.layer == Some(layer.id) && (node.model.is_some() || !node.children.is_empty()))
+                    {
+                        Some(scene) => {
+                            parts.push(ScenePart {
+                                name: layer.name.clone().unwrap_or_else(|| format!("Layer-{}", layer.id)),
+                                scene
+                            });
+                        },
+                        None => {}
+                    }
+                }
+            },
+            SplitMode::Node => {
+                let mut named: Vec<&SceneNode> = Vec::new();
+                collect_named(&self.root, &mut named);
+                for node in named
+                {
+                    match self.filter(|other| other.id == node.id)
+                    {
+                        Some(scene) => {
+                            parts.push(ScenePart {
+                                name: node.label(),
+                                scene
+                            });
+                        },
+                        None => {}
+                    }
+                }
+            }
+        }
+
+        return parts;
+    }
+
+    /// Flattens the scene into the models it places, in depth first order
+    pub fn instances(&self, models: &[dot_vox::Model]) -> Vec<Instance>
+    {
+        let mut instances: Vec<Instance> = Vec::new();
+        collect_instances(&self.root, &Transform::IDENTITY, models, &mut instances);
+        return instances;
+    }
+}
+
+/// Keeps `node` whole if `keep` accepts it, otherwise keeps only the branches
+/// leading to accepted nodes. Returns `None` when nothing is left.
+fn prune<F: Fn(&SceneNode) -> bool>(node: &SceneNode, keep: &F) -> Option<SceneNode>
+{
+    if keep(node)
+    {
+        return Some(node.clone());
+    }
+
+    let children: Vec<SceneNode> = node.children.iter().filter_map(|child| prune(child, keep)).collect();
+    if children.is_empty()
+    {
+        return None;
+    }
+
+    return Some(SceneNode {
+        model: None,
+        frames: Vec::new(),
+        children,
+        ..node.clone()
+    });
+}
+
+/// Applies the model, layer and visibility parts of `selection` to `node` and
+/// the nodes below it. Groups emptied by the selection are dropped.
+fn select_node(node: &SceneNode, selection: &Selection, layers: &[Layer]) -> Option<SceneNode>
+{
+    let layer = node.layer.and_then(|id| layers.iter().find(|layer| layer.id == id));
+    let hidden = node.hidden || layer.is_some_and(|layer| layer.hidden);
+    if hidden && !selection.include_hidden
+    {
+        return None;
+    }
+
+    match node.layer
+    {
+        Some(id) if !selection.layers.is_empty() => {
+            let name = layer.and_then(|layer| layer.name.as_deref());
+            if !selection.layers.iter().any(|wanted| *wanted == id.to_string() || Some(wanted.as_str()) == name)
+            {
+                return None;
+            }
+        },
+        _ => {}
+    }
+
+    let selected = |model: &usize| selection.models.is_empty() || selection.models.iter().any(|range| range.contains(model));
+    let frames: Vec<usize> = node.frames.iter().copied().filter(selected).collect();
+    let model = node.model.filter(selected).or_else(|| frames.first().copied());
+    let children: Vec<SceneNode> = node.children.iter().filter_map(|child| select_node(child, selection, layers)).collect();
+    if model.is_none() && children.is_empty()
+    {
+        return None;
+    }
+
+    return Some(SceneNode {
+        model,
+        frames,
+        children,
+        ..node.clone()
+    });
+}
+
+fn frame_count(node: &SceneNode) -> usize
+{
+    return node.children.iter().map(frame_count).fold(node.frames.len(), usize::max);
+}
+
+/// Returns `node` with every animated shape below it showing its model for
+/// `frame`, shapes with fewer frames holding their last one
+fn frame_node(node: &SceneNode, frame: usize) -> SceneNode
+{
+    let model = node.frames.get(frame).or_else(|| node.frames.last()).copied().or(node.model);
+    return SceneNode {
+        model,
+        frames: model.into_iter().collect(),
+        children: node.children.iter().map(|child| frame_node(child, frame)).collect(),
+        ..node.clone()
+    };
+}
+
+fn collect_named<'a>(node: &'a SceneNode, named: &mut Vec<&'a SceneNode>)
+{
+    if node.name.is_some()
+    {
+        named.push(node);
+    }
+    for child in &node.children
+    {
+        collect_named(child, named);
+    }
+}
+
+fn collect_models(node: &SceneNode, models: &mut BTreeSet<usize>)
+{
+    match node.model
+    {
+        Some(model) => {
+            models.insert(model);
+        },
+        None => {}
+    }
+    for child in &node.children
+    {
+        collect_models(child, models);
+    }
+}
+
+fn collect_instances(node: &SceneNode, parent: &Transform, models: &[dot_vox::Model], instances: &mut Vec<Instance>)
+{
+    let transform = parent.then(&node.transform);
+    match node.model
+    {
+        Some(model) => {
+            instances.push(Instance {
+                model,
+                name: node.label(),
+                layer: node.layer,
+                hidden: node.hidden,
+                transform: transform.then(&model_pivot(&models[model].size))
+            });
+        },
+        None => {}
+    }
+
+    for child in &node.children
+    {
+        collect_instances(child, &transform, models, instances);
+    }
+}
+
+/// A scene graph node as stored in the file, before the graph is resolved
+enum RawNode
+{
+    Transform {
+        attributes: HashMap<String, String>,
+        child: u32,
+        layer: i32,
+        frame: HashMap<String, String>
+    },
+    Group {
+        children: Vec<u32>
+    },
+    Shape {
+        models: Vec<usize>
+    }
+}
+
+/// Resolves the transform node `id` and everything below it. `depth` guards
+/// against files whose nodes form a cycle.
+fn build_node(nodes: &HashMap<u32, RawNode>, id: u32, depth: usize) -> Result<SceneNode>
+{
+    if depth > nodes.len()
+    {
+        return Err(VoxportError::Parse("scene graph contains a cycle".to_string()));
+    }
+
+    let (attributes, child, layer, frame) = match nodes.get(&id)
+    {
+        Some(RawNode::Transform { attributes, child, layer, frame }) => (attributes, *child, *layer, frame),
+        _ => {
+            return Err(VoxportError::Parse(format!("scene node {} is not a transform", id)));
+        }
+    };
+
+    let mut node = SceneNode {
+        id,
+        name: attributes.get("_name").cloned(),
+        hidden: attributes.get("_hidden").is_some_and(|hidden| hidden == "1"),
+        layer: if layer >= 0 { Some(layer as u32) } else { None },
+        transform: parse_frame(frame)?,
+        model: None,
+        frames: Vec::new(),
+        children: Vec::new()
+    };
+
+    match nodes.get(&child)
+    {
+        Some(RawNode::Group { children }) => {
+            for grandchild in children
+            {
+                node.children.push(build_node(nodes, *grandchild, depth + 1)?);
+            }
+        },
+        Some(RawNode::Shape { models }) => {
+            node.model = models.first().copied();
+            node.frames = models.clone();
+        },
+        _ => {
+            return Err(VoxportError::Parse(format!("transform node {} has no group or shape below it", id)));
+        }
+    }
+
+    return Ok(node);
+}
+
+/// Reads the rotation `_r` and translation `_t` of a transform frame
+fn parse_frame(frame: &HashMap<String, String>) -> Result<Transform>
+{
+    let mut translation = [0; 3];
+    match frame.get("_t")
+    {
+        Some(value) => {
+            let parts: Vec<&str> = value.split_whitespace().collect();
+            if parts.len() != 3
+            {
+                return Err(VoxportError::Parse(format!("invalid translation {:?}", value)));
+            }
+            for (axis, part) in parts.iter().enumerate()
+            {
+                translation[axis] = part.parse().map_err(|_| VoxportError::Parse(format!("invalid translation {:?}", value)))?;
+            }
+        },
+        None => {}
+    }
+
+    match frame.get("_r")
+    {
+        Some(value) => {
+            let packed: u8 = value.trim().parse().map_err(|_| VoxportError::Parse(format!("invalid rotation {:?}", value)))?;
+            return Transform::from_packed(packed, translation);
+        },
+        None => {
+            return Ok(Transform::from_translation(translation));
+        }
+    }
+}
+
+/// Returns the id and content of every chunk inside the `MAIN` chunk
+pub(crate) fn read_chunks(bytes: &[u8]) -> Result<Vec<(&[u8; 4], &[u8])>>
+{
+    let mut reader = Reader {
+        bytes,
+        offset: 8 // "VOX " and the version
+    };
+    if reader.take(4)? != b"MAIN"
+    {
+        return Err(VoxportError::Parse("missing MAIN chunk".to_string()));
+    }
+    let content_size = reader.u32()? as usize;
+    let children_size = reader.u32()? as usize;
+    reader.take(content_size)?;
+    let end = reader.offset.checked_add(children_size).filter(|end| *end <= bytes.len())
+        .ok_or_else(|| VoxportError::Parse("truncated MAIN chunk".to_string()))?;
+
+    let mut chunks: Vec<(&[u8; 4], &[u8])> = Vec::new();
+    while reader.offset < end
+    {
+        let id: &[u8; 4] = reader.take(4)?.try_into().unwrap();
+        let content_size = reader.u32()? as usize;
+        let children_size = reader.u32()? as usize;
+        chunks.push((id, reader.take(content_size)?));
+        reader.take(children_size)?;
+    }
+
+    return Ok(chunks);
+}
+
+/// Reads the little endian values of a chunk, failing instead of panicking on truncated input
+struct Reader<'a>
+{
+    bytes: &'a [u8],
+    offset: usize
+}
+
+impl<'a> Reader<'a>
+{
+    fn take(&mut self, length: usize) -> Result<&'a [u8]>
+    {
+        let end = self.offset.checked_add(length).filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| VoxportError::Parse("truncated chunk".to_string()))?;
+        let slice = &self.bytes[self.offset..end];
+        self.offset = end;
+        return Ok(slice);
+    }
+
+    fn u32(&mut self) -> Result<u32>
+    {
+        return Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()));
+    }
+
+    fn i32(&mut self) -> Result<i32>
+    {
+        return Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()));
+    }
+
+    fn string(&mut self) -> Result<String>
+    {
+        let length = self.u32()? as usize;
+        return Ok(String::from_utf8_lossy(self.take(length)?).into_owned());
+    }
+
+    fn dict(&mut self) -> Result<HashMap<String, String>>
+    {
+        let count = self.u32()?;
+        let mut dict: HashMap<String, String> = HashMap::new();
+        for _ in 0..count
+        {
+            let key = self.string()?;
+            let value = self.string()?;
+            dict.insert(key, value);
+        }
+        return Ok(dict);
+    }
+}
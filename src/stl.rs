@@ -0,0 +1,167 @@
+use std::io::{BufWriter, Write};
+
+use crate::color::Color;
+use crate::error::Result;
+use crate::mesh::{IndexedTriangle, MeshOptions, Normal, Triangle, Vertex};
+use crate::scene::Scene;
+
+/// Convention for storing the facet color in the 16-bit attribute field of binary STL
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum StlColor
+{
+    /// VisCAM and SolidView: blue in bits 0-4, green in 5-9, red in 10-14 and
+    /// bit 15 set for a valid color
+    VisCam,
+    /// Materialise Magics: red in bits 0-4, green in 5-9, blue in 10-14 and
+    /// bit 15 cleared for a facet color instead of the `COLOR=` default of the header
+    Materialise
+}
+
+impl StlColor
+{
+    /// Packs `color` into an attribute field, dropping the alpha channel
+    pub fn encode(self, color: Color) -> u16
+    {
+        let five_bits = |channel: u8| (channel as u16 * 31 + 127) / 255;
+        match self
+        {
+            StlColor::VisCam => 0x8000 | five_bits(color.r) << 10 | five_bits(color.g) << 5 | five_bits(color.b),
+            StlColor::Materialise => five_bits(color.b) << 10 | five_bits(color.g) << 5 | five_bits(color.r)
+        }
+    }
+
+    fn header(self) -> [u8; 80]
+    {
+        let mut header = [0u8; 80];
+        match self
+        {
+            StlColor::VisCam => {},
+            StlColor::Materialise => {
+                // Default color of facets without one of their own, opaque white
+                header[..10].copy_from_slice(b"COLOR=\xFF\xFF\xFF\xFF");
+            }
+        }
+        return header;
+    }
+}
+
+/// Encoding of the STL body
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum StlFormat
+{
+    /// Binary with empty attribute fields
+    Binary,
+    /// Binary with the palette color of every facet in its attribute field
+    ColorBinary(StlColor),
+    /// Text, which has no room for colors
+    Ascii
+}
+
+impl Into<stl_io::Normal> for Normal
+{
+    fn into(self) -> stl_io::Normal
+    {
+        stl_io::Normal::new([self.x, self.y, self.z])
+    }
+}
+
+impl Into<stl_io::Vertex> for Vertex
+{
+    fn into(self) -> stl_io::Vertex
+    {
+        stl_io::Vertex::new([self.x, self.y, self.z])
+    }
+}
+
+impl Into<stl_io::Triangle> for Triangle
+{
+    fn into(self) -> stl_io::Triangle
+    {
+        stl_io::Triangle {
+            normal: self.normal.into(),
+            vertices: [self.a.into(), self.b.into(), self.c.into()]
+        }
+    }
+}
+
+impl Into<stl_io::IndexedTriangle> for IndexedTriangle
+{
+    fn into(self) -> stl_io::IndexedTriangle
+    {
+        stl_io::IndexedTriangle {
+            normal: self.normal.into(),
+            vertices: [self.a, self.b, self.c]
+        }
+    }
+}
+
+fn write_color_stl<W: Write>(triangles: &[Triangle], palette: &[u32], color: StlColor, writer: &mut W) -> Result<()>
+{
+    let mut stl = BufWriter::new(writer);
+    stl.write_all(&color.header())?;
+    stl.write_all(&(triangles.len() as u32).to_le_bytes())?;
+    for triangle in triangles
+    {
+        let normal = triangle.normal;
+        for value in [normal.x, normal.y, normal.z].iter()
+        {
+            stl.write_all(&value.to_le_bytes())?;
+        }
+        for vertex in [triangle.a, triangle.b, triangle.c].iter()
+        {
+            for value in [vertex.x, vertex.y, vertex.z].iter()
+            {
+                stl.write_all(&value.to_le_bytes())?;
+            }
+        }
+        stl.write_all(&color.encode(Color::from_palette(palette, triangle.color_index)).to_le_bytes())?;
+    }
+
+    stl.flush()?;
+    return Ok(());
+}
+
+fn write_ascii_stl<W: Write>(triangles: &[Triangle], writer: &mut W) -> Result<()>
+{
+    let mut stl = BufWriter::new(writer);
+    writeln!(stl, "solid voxport")?;
+    for triangle in triangles
+    {
+        let normal = triangle.normal;
+        writeln!(stl, "  facet normal {:e} {:e} {:e}", normal.x, normal.y, normal.z)?;
+        writeln!(stl, "    outer loop")?;
+        for vertex in [triangle.a, triangle.b, triangle.c].iter()
+        {
+            writeln!(stl, "      vertex {:e} {:e} {:e}", vertex.x, vertex.y, vertex.z)?;
+        }
+        writeln!(stl, "    endloop")?;
+        writeln!(stl, "  endfacet")?;
+    }
+    writeln!(stl, "endsolid voxport")?;
+
+    stl.flush()?;
+    return Ok(());
+}
+
+/// Meshes every model placed by `scene` and writes them as a single STL in `format`
+pub fn convert_vox_stl<W: Write>(in_data: &dot_vox::DotVoxData, scene: &Scene, options: &MeshOptions, format: StlFormat, writer: &mut W) -> Result<()>
+{
+    let mut triangles = crate::mesh::convert_vox(in_data, scene, options)?;
+    crate::mesh::scale_triangles(&mut triangles, options.position_scale());
+
+    match format
+    {
+        StlFormat::Binary => {
+            let stl_triangles: Vec<stl_io::Triangle>;
+            stl_triangles = triangles.iter().map(|triangle| (*triangle).into()).collect::<Vec<_>>();
+            stl_io::write_stl(writer, stl_triangles.iter())?;
+        },
+        StlFormat::ColorBinary(color) => {
+            write_color_stl(&triangles, &in_data.palette, color, writer)?;
+        },
+        StlFormat::Ascii => {
+            write_ascii_stl(&triangles, writer)?;
+        }
+    }
+    return Ok(());
+}
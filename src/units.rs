@@ -0,0 +1,65 @@
+//! Physical size of voxels, for exports that shouldn't count one unit per voxel.
+
+/// Length unit of exported positions
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Unit
+{
+    Millimeter,
+    Centimeter,
+    Meter
+}
+
+impl Unit
+{
+    /// Length of the unit in meters
+    pub fn meters(self) -> f64
+    {
+        match self
+        {
+            Unit::Millimeter => 0.001,
+            Unit::Centimeter => 0.01,
+            Unit::Meter => 1.0
+        }
+    }
+
+    /// Name of the unit, as COLLADA writes it
+    pub fn name(self) -> &'static str
+    {
+        match self
+        {
+            Unit::Millimeter => "millimeter",
+            Unit::Centimeter => "centimeter",
+            Unit::Meter => "meter"
+        }
+    }
+
+    /// Parses the symbol of a unit, like `mm`
+    pub fn from_symbol(symbol: &str) -> Option<Self>
+    {
+        match symbol
+        {
+            "mm" => Some(Unit::Millimeter),
+            "cm" => Some(Unit::Centimeter),
+            "m" => Some(Unit::Meter),
+            _ => None
+        }
+    }
+}
+
+/// Edge length of a voxel
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct VoxelSize
+{
+    /// Length in `unit`
+    pub length: f32,
+    pub unit: Unit
+}
+
+impl VoxelSize
+{
+    /// Edge length of a voxel in meters
+    pub fn meters(&self) -> f32
+    {
+        return (self.length as f64 * self.unit.meters()) as f32;
+    }
+}
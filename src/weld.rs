@@ -0,0 +1,80 @@
+//! Vertex welding: maps positions and normals onto shared index lists in linear time.
+
+use std::collections::HashMap;
+
+use crate::mesh::{Normal, Vertex};
+
+/// Coordinates are multiplied by this before being rounded onto the integer
+/// lattice used as hash keys, so points closer than `1 / WELD_SCALE` merge
+pub const WELD_SCALE: f32 = 1024.0;
+
+pub(crate) type LatticeKey = (i64, i64, i64);
+
+pub(crate) fn lattice_key(x: f32, y: f32, z: f32) -> LatticeKey
+{
+    return (
+        (x * WELD_SCALE).round() as i64,
+        (y * WELD_SCALE).round() as i64,
+        (z * WELD_SCALE).round() as i64
+    );
+}
+
+/// Builds deduplicated vertex and normal lists, handing out the index of each
+/// vertex or normal as it is added
+#[derive(Default)]
+pub struct Welder
+{
+    pub vertices: Vec<Vertex>,
+    pub normals: Vec<Normal>,
+    vertex_indices: HashMap<LatticeKey, usize>,
+    normal_indices: HashMap<LatticeKey, usize>
+}
+
+impl Welder
+{
+    pub fn new() -> Self
+    {
+        return Self::default();
+    }
+
+    /// Creates a welder that keeps appending to existing vertex and normal lists.
+    /// Entries already in the lists keep their indices, even ones close enough
+    /// to weld onto an earlier entry, which is what new points near both get.
+    pub fn from_lists(vertices: Vec<Vertex>, normals: Vec<Normal>) -> Self
+    {
+        let mut welder = Self::new();
+        for (index, vertex) in vertices.iter().enumerate()
+        {
+            welder.vertex_indices.entry(lattice_key(vertex.x, vertex.y, vertex.z)).or_insert(index);
+        }
+
+        for (index, normal) in normals.iter().enumerate()
+        {
+            welder.normal_indices.entry(lattice_key(normal.x, normal.y, normal.z)).or_insert(index);
+        }
+
+        welder.vertices = vertices;
+        welder.normals = normals;
+        return welder;
+    }
+
+    /// Returns the index of `vertex`, adding it to the list if it hasn't been seen yet
+    pub fn vertex(&mut self, vertex: Vertex) -> usize
+    {
+        let vertices = &mut self.vertices;
+        return *self.vertex_indices.entry(lattice_key(vertex.x, vertex.y, vertex.z)).or_insert_with(|| {
+            vertices.push(vertex);
+            vertices.len() - 1
+        });
+    }
+
+    /// Returns the index of `normal`, adding it to the list if it hasn't been seen yet
+    pub fn normal(&mut self, normal: Normal) -> usize
+    {
+        let normals = &mut self.normals;
+        return *self.normal_indices.entry(lattice_key(normal.x, normal.y, normal.z)).or_insert_with(|| {
+            normals.push(normal);
+            normals.len() - 1
+        });
+    }
+}
@@ -0,0 +1,90 @@
+#![allow(clippy::needless_return)]
+
+extern crate dot_vox;
+extern crate voxport;
+
+use voxport::mesh::{fill_cavities, get_voxel_idx, reorder_voxels};
+use voxport::{MeshOptions, Mesher};
+
+/// A 3x3x3 cube with its center cell left empty, and the cell in front of it
+/// as well if `open` is set
+fn hollow_cube(open: bool) -> dot_vox::Model
+{
+    let mut voxels: Vec<dot_vox::Voxel> = Vec::new();
+    for z in 0..3
+    {
+        for y in 0..3
+        {
+            for x in 0..3
+            {
+                let center = x == 1 && z == 1 && (y == 1 || (open && y == 2));
+                if !center
+                {
+                    voxels.push(dot_vox::Voxel { x, y, z, i: 1 });
+                }
+            }
+        }
+    }
+    return dot_vox::Model {
+        size: dot_vox::Size { x: 3, y: 3, z: 3 },
+        voxels
+    };
+}
+
+fn face_count(model: &dot_vox::Model, fill_cavities: bool) -> usize
+{
+    let options = MeshOptions {
+        mesher: Mesher::Naive,
+        fill_cavities,
+        ..MeshOptions::default()
+    };
+    return voxport::convert_model(model, &options).unwrap().len() / 2;
+}
+
+#[test]
+fn sealed_cavities_lose_their_walls()
+{
+    let model = hollow_cube(false);
+    assert_eq!(face_count(&model, false), 6 * 9 + 6);
+    assert_eq!(face_count(&model, true), 6 * 9);
+}
+
+#[test]
+fn cavities_open_to_the_outside_are_kept()
+{
+    // A pocket reaching the front of the cube
+    let model = hollow_cube(true);
+    assert_eq!(face_count(&model, true), face_count(&model, false));
+    assert_eq!(face_count(&model, true), 6 * 9 - 1 + 5 + 4);
+}
+
+#[test]
+fn filled_cavities_take_the_color_of_their_walls()
+{
+    // A 5x3x3 box split into a side of palette index 1 and one of index 2,
+    // with a cavity of three cells running through the middle
+    let mut voxels: Vec<dot_vox::Voxel> = Vec::new();
+    for z in 0..3
+    {
+        for y in 0..3
+        {
+            for x in 0..5
+            {
+                let cavity = (1..4).contains(&x) && y == 1 && z == 1;
+                if !cavity
+                {
+                    voxels.push(dot_vox::Voxel { x, y, z, i: if x < 2 { 1 } else { 2 } });
+                }
+            }
+        }
+    }
+    let size = dot_vox::Size { x: 5, y: 3, z: 3 };
+
+    let mut grid = reorder_voxels(&voxels, &size).unwrap();
+    fill_cavities(&mut grid, &size);
+    let filled: Vec<u8> = (1..4)
+        .map(|x| grid[get_voxel_idx(&dot_vox::Voxel { x, y: 1, z: 1, i: 0 }, &size)].unwrap().i)
+        .collect();
+    assert_eq!(filled, vec![1, 2, 2]);
+    assert!(grid.iter().all(|cell| cell.map(|voxel| voxel.i != 0).unwrap_or(false)));
+}
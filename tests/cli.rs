@@ -0,0 +1,149 @@
+//! Runs the voxport binary to check what its command line reports and writes.
+#![allow(clippy::needless_return)]
+
+extern crate stl_io;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// A fresh scratch directory for the outputs of test `name`
+fn scratch_dir(name: &str) -> PathBuf
+{
+    let dir = std::env::temp_dir().join(format!("voxport-cli-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    return dir;
+}
+
+fn voxport(args: &[&str]) -> Output
+{
+    return Command::new(env!("CARGO_BIN_EXE_voxport")).args(args).output().unwrap();
+}
+
+/// Panics unless the STL at `path` is a closed surface, running along every
+/// edge once in each direction
+fn assert_closed(path: &Path)
+{
+    let mut file = std::fs::File::open(path).unwrap();
+    let mesh = stl_io::read_stl(&mut file).unwrap();
+    assert!(!mesh.faces.is_empty(), "{} is empty", path.display());
+
+    let mut edges: HashMap<(usize, usize), i32> = HashMap::new();
+    for face in &mesh.faces
+    {
+        for i in 0..3
+        {
+            let (from, to) = (face.vertices[i], face.vertices[(i + 1) % 3]);
+            *edges.entry((from, to)).or_default() += 1;
+            *edges.entry((to, from)).or_default() -= 1;
+        }
+    }
+    assert!(edges.values().all(|count| *count == 0), "{} is not closed", path.display());
+}
+
+#[test]
+fn default_stl_is_closed()
+{
+    let dir = scratch_dir("stl");
+    for name in ["shield", "column"].iter()
+    {
+        let out_file = dir.join(format!("{}.stl", name));
+        let output = voxport(&["--stl", "-i", &format!("examples/{}.vox", name), "-o", out_file.to_str().unwrap()]);
+        assert_eq!(output.status.code(), Some(0));
+        assert_closed(&out_file);
+    }
+}
+
+#[test]
+fn empty_selection_writes_nothing()
+{
+    let dir = scratch_dir("selection");
+    let out_file = dir.join("shield.stl");
+    let output = voxport(&["--stl", "-i", "examples/shield.vox", "-o", out_file.to_str().unwrap(), "--models", "5-9"]);
+    assert_eq!(output.status.code(), Some(65));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no models selected"));
+    assert!(!out_file.exists());
+
+    let output = voxport(&["--stl", "-i", "examples/shield.vox", "-o", out_file.to_str().unwrap(), "--models", "0"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(out_file.exists());
+}
+
+#[test]
+fn single_animations_need_an_animated_format()
+{
+    let dir = scratch_dir("animation");
+    for (format, extension) in [("--stl", "stl"), ("--obj", "obj"), ("--ply", "ply")].iter()
+    {
+        let out_file = dir.join(format!("shield.{}", extension));
+        let output = voxport(&[format, "--animation", "single", "-i", "examples/shield.vox", "-o", out_file.to_str().unwrap()]);
+        assert_eq!(output.status.code(), Some(2), "{} took --animation single", format);
+        assert!(String::from_utf8_lossy(&output.stderr).contains("--dae|--gltf"));
+        assert!(!out_file.exists());
+    }
+
+    let out_file = dir.join("shield.glb");
+    let output = voxport(&["--gltf", "--animation", "single", "-i", "examples/shield.vox", "-o", out_file.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(out_file.exists());
+}
+
+#[test]
+fn invalid_frame_rates_are_usage_errors()
+{
+    let dir = scratch_dir("fps");
+    let out_file = dir.join("shield.glb");
+    let output = voxport(&["--gltf", "--animation", "single", "--fps", "0", "-i", "examples/shield.vox", "-o", out_file.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not a positive frame rate"));
+    assert!(!out_file.exists());
+}
+
+#[test]
+fn split_colors_are_closed_bodies()
+{
+    let dir = scratch_dir("split-colors");
+    let out_file = dir.join("shield.stl");
+    let output = voxport(&["--stl", "--split-colors", "-i", "examples/shield.vox", "-o", out_file.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    let parts: Vec<PathBuf> = std::fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().path()).collect();
+    assert!(parts.len() > 1);
+    for part in &parts
+    {
+        assert_closed(part);
+    }
+
+    let out_file = dir.join("greedy.stl");
+    let output = voxport(&["--stl", "--split-colors", "--mesher", "greedy", "-i", "examples/shield.vox", "-o", out_file.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("closed surface"));
+    assert!(!dir.join("greedy_color1.stl").exists());
+}
+
+#[test]
+fn hollowed_stl_is_closed()
+{
+    let dir = scratch_dir("hollow");
+    let out_file = dir.join("column.stl");
+    for args in [vec! { "--hollow", "2" }, vec! { "--hollow", "2", "--drain", "lowest" }].iter()
+    {
+        let mut all_args = vec! { "--stl", "-i", "examples/column.vox", "-o", out_file.to_str().unwrap() };
+        all_args.extend(args);
+        let output = voxport(&all_args);
+        assert_eq!(output.status.code(), Some(0), "{:?}", args);
+        assert_closed(&out_file);
+    }
+}
+
+#[test]
+fn truncated_input_is_a_parse_error()
+{
+    let dir = scratch_dir("truncated");
+    let in_file = dir.join("truncated.vox");
+    std::fs::write(&in_file, b"VOX \x96\x00\x00\x00MAIN").unwrap();
+    let out_file = dir.join("truncated.stl");
+    let output = voxport(&["--stl", "-i", in_file.to_str().unwrap(), "-o", out_file.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(65));
+    assert!(!out_file.exists());
+}
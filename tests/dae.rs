@@ -0,0 +1,434 @@
+//! Checks DAE output against the structure the COLLADA 1.4.1 schema requires:
+//! element order, unique ids, resolvable references and consistent counts.
+#![allow(clippy::needless_return)]
+
+extern crate dot_vox;
+extern crate voxport;
+extern crate xml;
+
+use std::collections::{HashMap, HashSet};
+
+use voxport::{Color, MeshOptions, PaletteTexture, Scene, TextureLayout};
+
+struct Element
+{
+    name: String,
+    attributes: HashMap<String, String>,
+    children: Vec<Element>,
+    text: String
+}
+
+impl Element
+{
+    fn attr(&self, name: &str) -> &str
+    {
+        return self.attributes.get(name).map(|value| value.as_str())
+            .unwrap_or_else(|| panic!("<{}> is missing its {} attribute", self.name, name));
+    }
+
+    fn child(&self, name: &str) -> &Element
+    {
+        return self.children.iter().find(|child| child.name == name)
+            .unwrap_or_else(|| panic!("<{}> has no <{}>", self.name, name));
+    }
+
+    fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a
+    {
+        return self.children.iter().filter(move |child| child.name == name);
+    }
+
+    fn walk<'a>(&'a self, elements: &mut Vec<&'a Element>)
+    {
+        elements.push(self);
+        for child in &self.children
+        {
+            child.walk(elements);
+        }
+    }
+}
+
+fn parse(bytes: &[u8]) -> Element
+{
+    let mut stack: Vec<Element> = vec![Element {
+        name: String::new(),
+        attributes: HashMap::new(),
+        children: Vec::new(),
+        text: String::new()
+    }];
+
+    for event in xml::reader::EventReader::new(bytes)
+    {
+        match event.expect("DAE output is not well-formed XML")
+        {
+            xml::reader::XmlEvent::StartElement { name, attributes, namespace } => {
+                if stack.len() == 1
+                {
+                    assert_eq!(namespace.get(""), Some("http://www.collada.org/2005/11/COLLADASchema"));
+                }
+                stack.push(Element {
+                    name: name.local_name,
+                    attributes: attributes.into_iter().map(|attr| (attr.name.local_name, attr.value)).collect(),
+                    children: Vec::new(),
+                    text: String::new()
+                });
+            },
+            xml::reader::XmlEvent::EndElement { .. } => {
+                let element = stack.pop().unwrap();
+                stack.last_mut().unwrap().children.push(element);
+            },
+            xml::reader::XmlEvent::Characters(text) => {
+                stack.last_mut().unwrap().text.push_str(&text);
+            },
+            _ => {}
+        }
+    }
+
+    let mut document = stack.pop().unwrap();
+    assert_eq!(document.children.len(), 1);
+    return document.children.pop().unwrap();
+}
+
+fn export(in_data: &dot_vox::DotVoxData, scene: &Scene) -> Element
+{
+    let mut bytes: Vec<u8> = Vec::new();
+    voxport::convert_vox_dae(in_data, scene, &MeshOptions::default(), &mut bytes).unwrap();
+    return parse(&bytes);
+}
+
+/// The effect bound to material `name`
+fn effect<'a>(collada: &'a Element, name: &str) -> &'a Element
+{
+    let material = collada.child("library_materials").children_named("material")
+        .find(|material| material.attr("name") == name)
+        .unwrap_or_else(|| panic!("no material {}", name));
+    let url = material.child("instance_effect").attr("url");
+    return collada.child("library_effects").children_named("effect")
+        .find(|effect| format!("#{}", effect.attr("id")) == url)
+        .unwrap();
+}
+
+fn count_values(element: &Element) -> usize
+{
+    return element.text.split_whitespace().count();
+}
+
+fn check_schema_structure(collada: &Element)
+{
+    assert_eq!(collada.name, "COLLADA");
+    assert_eq!(collada.attr("version"), "1.4.1");
+
+    // asset comes first and scene last, with only libraries in between
+    let names: Vec<&str> = collada.children.iter().map(|child| child.name.as_str()).collect();
+    assert_eq!(names.first(), Some(&"asset"));
+    assert_eq!(names.last(), Some(&"scene"));
+    for name in &names[1..names.len() - 1]
+    {
+        assert!(name.starts_with("library_"), "unexpected <{}> in <COLLADA>", name);
+    }
+
+    let asset = collada.child("asset");
+    let asset_names: Vec<&str> = asset.children.iter().map(|child| child.name.as_str()).collect();
+    assert_eq!(asset_names, vec!["contributor", "created", "modified", "unit", "up_axis"]);
+    for date in &["created", "modified"]
+    {
+        // xs:dateTime, e.g. 2020-12-22T10:00:00+00:00
+        let text = &asset.child(date).text;
+        assert_eq!(text.as_bytes()[4], b'-', "{} is not an xs:dateTime: {}", date, text);
+        assert_eq!(text.as_bytes()[10], b'T', "{} is not an xs:dateTime: {}", date, text);
+    }
+
+    let mut elements: Vec<&Element> = Vec::new();
+    collada.walk(&mut elements);
+
+    let mut ids: HashSet<&str> = HashSet::new();
+    for element in &elements
+    {
+        if let Some(id) = element.attributes.get("id")
+        {
+            assert!(ids.insert(id), "duplicate id {}", id);
+        }
+    }
+
+    for element in &elements
+    {
+        // Animation channels target a node through a sid path instead
+        let attributes: &[&str] = if element.name == "channel" { &["source"] } else { &["url", "source", "target"] };
+        for attribute in attributes
+        {
+            if let Some(reference) = element.attributes.get(*attribute)
+            {
+                let id = reference.strip_prefix('#')
+                    .unwrap_or_else(|| panic!("<{}> {} is not a URI fragment: {}", element.name, attribute, reference));
+                assert!(ids.contains(id), "<{}> {} points at missing id {}", element.name, attribute, reference);
+            }
+        }
+    }
+
+    for geometry in collada.child("library_geometries").children_named("geometry")
+    {
+        let mesh = geometry.child("mesh");
+        let mesh_names: Vec<&str> = mesh.children.iter().map(|child| child.name.as_str()).collect();
+        let vertices_at = mesh_names.iter().position(|name| *name == "vertices").expect("mesh without <vertices>");
+        assert!(vertices_at > 0);
+        assert!(mesh_names[..vertices_at].iter().all(|name| *name == "source"));
+        assert!(mesh_names[vertices_at + 1..].iter().all(|name| *name == "triangles"));
+
+        let mut source_sizes: HashMap<String, usize> = HashMap::new();
+        for source in mesh.children_named("source")
+        {
+            let float_array = source.child("float_array");
+            assert_eq!(float_array.attr("count").parse::<usize>().unwrap(), count_values(float_array));
+
+            let accessor = source.child("technique_common").child("accessor");
+            let count: usize = accessor.attr("count").parse().unwrap();
+            let stride: usize = accessor.attr("stride").parse().unwrap();
+            assert_eq!(accessor.children_named("param").count(), stride);
+            assert_eq!(count * stride, count_values(float_array));
+            source_sizes.insert(format!("#{}", source.attr("id")), count);
+        }
+
+        let vertices = mesh.child("vertices");
+        let positions = vertices.child("input").attr("source");
+        source_sizes.insert(format!("#{}", vertices.attr("id")), source_sizes[positions]);
+
+        for triangles in mesh.children_named("triangles")
+        {
+            let inputs: Vec<&Element> = triangles.children_named("input").collect();
+            let stride = inputs.len();
+            let indices: Vec<usize> = triangles.child("p").text.split_whitespace().map(|value| value.parse().unwrap()).collect();
+            let count: usize = triangles.attr("count").parse().unwrap();
+            assert_eq!(count * 3 * stride, indices.len());
+
+            for input in inputs
+            {
+                let offset: usize = input.attr("offset").parse().unwrap();
+                let size = source_sizes[input.attr("source")];
+                for index in indices.iter().skip(offset).step_by(stride)
+                {
+                    assert!(*index < size, "{} index {} out of range", input.attr("semantic"), index);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the geometry url instanced by each node of the visual scene
+fn instanced_geometries(collada: &Element) -> Vec<String>
+{
+    let mut elements: Vec<&Element> = Vec::new();
+    collada.child("library_visual_scenes").child("visual_scene").walk(&mut elements);
+    return elements.iter()
+        .filter(|element| element.name == "instance_geometry")
+        .map(|element| element.attr("url").to_string())
+        .collect();
+}
+
+#[test]
+fn examples_match_schema_structure()
+{
+    for path in &["examples/shield.vox", "examples/column.vox"]
+    {
+        let (in_data, scene) = voxport::load_scene(path).unwrap();
+        let collada = export(&in_data, &scene);
+        check_schema_structure(&collada);
+        assert_eq!(instanced_geometries(&collada).len(), in_data.models.len());
+    }
+}
+
+#[test]
+fn faces_carry_normals_colors_and_palette_materials()
+{
+    let (in_data, scene) = voxport::load_scene("examples/shield.vox").unwrap();
+    let collada = export(&in_data, &scene);
+    let rgba = |index: u8| -> Vec<f32> {
+        let color = Color::from_palette(&in_data.palette, index);
+        return [color.r, color.g, color.b, color.a].iter().map(|channel| *channel as f32 / 255.0).collect();
+    };
+    let floats = |element: &Element| -> Vec<f32> {
+        return element.text.split_whitespace().map(|value| value.parse().unwrap()).collect();
+    };
+
+    // One material per palette index in use, with the color of its entry
+    let used = voxport::used_palette_indices(&in_data);
+    let names: HashSet<String> = collada.child("library_materials").children_named("material")
+        .map(|material| material.attr("name").to_string())
+        .collect();
+    assert_eq!(names, used.iter().map(|index| format!("palette_{}", index)).collect());
+    for index in &used
+    {
+        let technique = effect(&collada, &format!("palette_{}", index)).child("profile_COMMON").child("technique");
+        let diffuse = technique.children[0].child("diffuse").child("color");
+        assert_eq!(floats(diffuse), rgba(*index));
+    }
+
+    for geometry in collada.child("library_geometries").children_named("geometry")
+    {
+        let mesh = geometry.child("mesh");
+        let source = |id: &str| -> Vec<f32> {
+            let source = mesh.children_named("source").find(|source| format!("#{}", source.attr("id")) == id).unwrap();
+            return floats(source.child("float_array"));
+        };
+
+        for triangles in mesh.children_named("triangles")
+        {
+            let index: u8 = triangles.attr("material").strip_prefix("palette_").unwrap().parse().unwrap();
+            let inputs: HashMap<&str, &Element> = triangles.children_named("input").map(|input| (input.attr("semantic"), input)).collect();
+            let stride = inputs.len();
+            let indices: Vec<usize> = triangles.child("p").text.split_whitespace().map(|value| value.parse().unwrap()).collect();
+            let values = |semantic: &str| -> Vec<usize> {
+                let offset: usize = inputs[semantic].attr("offset").parse().unwrap();
+                return indices.iter().skip(offset).step_by(stride).copied().collect();
+            };
+
+            let normals = source(inputs["NORMAL"].attr("source"));
+            for normal in values("NORMAL")
+            {
+                let normal = &normals[normal * 3..normal * 3 + 3];
+                assert_eq!(normal.iter().map(|value| value.abs()).sum::<f32>(), 1.0, "{:?} is not an axis", normal);
+            }
+
+            let colors = source(inputs["COLOR"].attr("source"));
+            for color in values("COLOR")
+            {
+                assert_eq!(colors[color * 4..color * 4 + 4].to_vec(), rgba(index));
+            }
+        }
+    }
+
+    // Every node binds the materials its geometry's triangles use
+    let mut elements: Vec<&Element> = Vec::new();
+    collada.child("library_visual_scenes").walk(&mut elements);
+    for instance in elements.iter().filter(|element| element.name == "instance_geometry")
+    {
+        let symbols: HashSet<&str> = instance.child("bind_material").child("technique_common").children_named("instance_material")
+            .map(|material| {
+                assert_eq!(material.attr("target"), format!("#{}-material", material.attr("symbol")));
+                material.attr("symbol")
+            })
+            .collect();
+        let geometry = collada.child("library_geometries").children_named("geometry")
+            .find(|geometry| format!("#{}", geometry.attr("id")) == instance.attr("url"))
+            .unwrap();
+        let used: HashSet<&str> = geometry.child("mesh").children_named("triangles").map(|triangles| triangles.attr("material")).collect();
+        assert_eq!(symbols, used);
+    }
+}
+
+#[test]
+fn every_model_gets_its_own_geometry_and_node()
+{
+    let mut in_data = voxport::load("examples/shield.vox").unwrap();
+    let voxels = in_data.models[0].voxels[..10].to_vec();
+    in_data.models.push(dot_vox::Model {
+        size: in_data.models[0].size,
+        voxels
+    });
+    in_data.models.push(dot_vox::Model {
+        size: dot_vox::Size { x: 1, y: 1, z: 1 },
+        voxels: vec![dot_vox::Voxel { x: 0, y: 0, z: 0, i: 3 }]
+    });
+
+    let collada = export(&in_data, &Scene::from_models(&in_data));
+    check_schema_structure(&collada);
+
+    let urls = instanced_geometries(&collada);
+    assert_eq!(urls.len(), 3);
+    let unique: HashSet<&String> = urls.iter().collect();
+    assert_eq!(unique.len(), 3);
+    assert_eq!(collada.child("library_geometries").children_named("geometry").count(), 3);
+}
+
+#[test]
+fn animation_drives_every_frame_node()
+{
+    let mut in_data = voxport::load("examples/shield.vox").unwrap();
+    in_data.models.push(dot_vox::Model {
+        size: dot_vox::Size { x: 1, y: 1, z: 1 },
+        voxels: vec![dot_vox::Voxel { x: 0, y: 0, z: 0, i: 3 }]
+    });
+    let frames = Scene::from_models(&in_data).frames();
+    assert_eq!(frames.len(), 2);
+
+    let mut bytes: Vec<u8> = Vec::new();
+    voxport::convert_animation_dae(&in_data, &frames, 12.0, &MeshOptions::default(), &mut bytes).unwrap();
+    let collada = parse(&bytes);
+    check_schema_structure(&collada);
+
+    let frame_nodes: Vec<&Element> = collada.child("library_visual_scenes").child("visual_scene").children_named("node").collect();
+    assert_eq!(frame_nodes.len(), 2);
+    for (animation, node) in collada.child("library_animations").children_named("animation").zip(frame_nodes)
+    {
+        let channel = animation.child("channel");
+        assert_eq!(channel.attr("target"), format!("{}/{}", node.attr("id"), node.child("scale").attr("sid")));
+
+        let sampler = animation.child("sampler");
+        let keys: Vec<usize> = sampler.children_named("input")
+            .map(|input| {
+                let id = &input.attr("source")[1..];
+                let source = animation.children_named("source").find(|source| source.attr("id") == id).unwrap();
+                source.child("technique_common").child("accessor").attr("count").parse().unwrap()
+            })
+            .collect();
+        // One key per frame plus the one holding the last frame
+        assert_eq!(keys, vec![3, 3, 3]);
+    }
+}
+
+#[test]
+fn palette_texture_replaces_materials()
+{
+    let (in_data, scene) = voxport::load_scene("examples/shield.vox").unwrap();
+    let options = MeshOptions {
+        texture: Some(PaletteTexture {
+            layout: TextureLayout::Grid,
+            uri: "shield_palette.png".to_string()
+        }),
+        ..MeshOptions::default()
+    };
+    let mut bytes: Vec<u8> = Vec::new();
+    voxport::convert_vox_dae(&in_data, &scene, &options, &mut bytes).unwrap();
+    let collada = parse(&bytes);
+    check_schema_structure(&collada);
+
+    let image = collada.child("library_images").child("image");
+    assert_eq!(image.child("init_from").text, "shield_palette.png");
+    assert_eq!(collada.child("library_materials").children_named("material").count(), 1);
+    for geometry in collada.child("library_geometries").children_named("geometry")
+    {
+        for triangles in geometry.child("mesh").children_named("triangles")
+        {
+            assert!(triangles.children_named("input").any(|input| input.attr("semantic") == "TEXCOORD"));
+        }
+    }
+}
+
+#[test]
+fn materials_become_shader_parameters()
+{
+    let mut in_data = voxport::load("examples/shield.vox").unwrap();
+    // MATL ids are one above the palette index they belong to
+    let properties = [
+        (14, vec![("_type", "_metal"), ("_metal", "1"), ("_rough", "0.5")]),
+        (149, vec![("_type", "_emit"), ("_emit", "1")]),
+        (151, vec![("_type", "_glass"), ("_trans", "0.5"), ("_ior", "0.5")])
+    ];
+    for (index, pairs) in properties.iter()
+    {
+        let material = in_data.materials.iter_mut().find(|material| material.id == index + 1).unwrap();
+        material.properties = pairs.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect();
+    }
+    let collada = export(&in_data, &Scene::from_models(&in_data));
+    check_schema_structure(&collada);
+
+    // Shader parameters have to follow the order of the schema
+    let shader = |name: &str| -> Vec<String> {
+        let technique = effect(&collada, name).child("profile_COMMON").child("technique");
+        assert_eq!(technique.children.len(), 1);
+        let shader = &technique.children[0];
+        return std::iter::once(shader.name.clone()).chain(shader.children.iter().map(|child| child.name.clone())).collect();
+    };
+    assert_eq!(shader("palette_14"), vec!["phong", "diffuse", "specular", "shininess", "reflective", "reflectivity"]);
+    assert_eq!(shader("palette_149"), vec!["lambert", "emission", "diffuse"]);
+    assert_eq!(shader("palette_151"), vec!["lambert", "diffuse", "transparent", "transparency", "index_of_refraction"]);
+    assert_eq!(shader("palette_159"), vec!["lambert", "diffuse"]);
+}
@@ -0,0 +1,74 @@
+#![allow(clippy::needless_return)]
+
+extern crate voxport;
+
+use voxport::VoxportError;
+
+fn io_error() -> std::io::Error
+{
+    return std::io::Error::other("failed");
+}
+
+#[test]
+fn exit_codes_tell_failures_apart()
+{
+    assert_eq!(VoxportError::Parse(String::new()).exit_code(), 65);
+    assert_eq!(VoxportError::InvalidInput(String::new()).exit_code(), 65);
+    assert_eq!(VoxportError::EmptySelection.exit_code(), 65);
+    assert_eq!(VoxportError::NoInput(io_error()).exit_code(), 66);
+    assert_eq!(VoxportError::UnsupportedModel(String::new()).exit_code(), 69);
+    assert_eq!(VoxportError::Export(String::new()).exit_code(), 70);
+    assert_eq!(VoxportError::Io(io_error()).exit_code(), 74);
+}
+
+#[test]
+fn missing_input_is_not_an_output_error()
+{
+    let err = voxport::load("examples/missing.vox").unwrap_err();
+    assert_eq!(err.exit_code(), 66);
+    let err = voxport::load_scene("examples/missing.vox").unwrap_err();
+    assert_eq!(err.exit_code(), 66);
+}
+
+#[test]
+fn malformed_input_is_a_parse_error()
+{
+    let bytes = std::fs::read("examples/shield.vox").unwrap();
+    let inputs: Vec<&[u8]> = vec! {
+        b"",
+        b"VOX",
+        b"PNG \x96\x00\x00\x00MAIN",
+        &bytes[..8],
+        &bytes[..19],
+        &bytes[..20],
+        &bytes[..bytes.len() / 2]
+    };
+    for input in inputs
+    {
+        match voxport::load_bytes(input)
+        {
+            Err(VoxportError::Parse(_)) => {},
+            other => panic!("{} bytes: expected a parse error, got {:?}", input.len(), other.map(|_| ()))
+        }
+    }
+}
+
+#[test]
+fn truncated_main_chunk_is_a_parse_error()
+{
+    let inputs: Vec<&[u8]> = vec! {
+        b"VOX \x96\x00\x00\x00MAIN",
+        b"VOX \x96\x00\x00\x00MAIN\x00\x00\x00\x00",
+        b"VOX \x96\x00\x00\x00MAIN\x00\x00\x00\x00\x10\x00\x00\x00",
+        b"VOX \x96\x00\x00\x00MAIN\x04\x00\x00\x00\x00\x00\x00\x00",
+        b"VOX \x96\x00\x00\x00SIZE\x00\x00\x00\x00\x00\x00\x00\x00"
+    };
+    for input in inputs
+    {
+        match voxport::load_bytes(input)
+        {
+            Err(VoxportError::Parse(_)) => {},
+            other => panic!("{:?}: expected a parse error, got {:?}", input, other.map(|_| ()))
+        }
+    }
+}
@@ -0,0 +1,178 @@
+#![allow(clippy::needless_return)]
+
+extern crate dot_vox;
+extern crate serde_json;
+extern crate voxport;
+
+use std::convert::TryInto;
+
+use serde_json::Value;
+use voxport::{Color, MeshOptions, Scene};
+
+fn index(value: &Value) -> usize
+{
+    return value.as_u64().unwrap() as usize;
+}
+
+/// The `f32` or `u32` components of accessor `accessor`, read from `bin`
+fn read_accessor(document: &Value, bin: &[u8], accessor: usize) -> Vec<f64>
+{
+    let accessor = &document["accessors"][accessor];
+    let view = &document["bufferViews"][index(&accessor["bufferView"])];
+    let offset = index(&view["byteOffset"]);
+    let bytes = &bin[offset..offset + index(&view["byteLength"])];
+
+    let components = match accessor["type"].as_str().unwrap()
+    {
+        "SCALAR" => 1,
+        "VEC3" => 3,
+        other => panic!("unexpected accessor type {}", other)
+    };
+    assert_eq!(bytes.len(), index(&accessor["count"]) * components * 4);
+    return bytes.chunks(4)
+        .map(|chunk| {
+            let chunk: [u8; 4] = chunk.try_into().unwrap();
+            match accessor["componentType"].as_u64().unwrap()
+            {
+                5126 => f32::from_le_bytes(chunk) as f64,
+                5125 => u32::from_le_bytes(chunk) as f64,
+                other => panic!("unexpected component type {}", other)
+            }
+        })
+        .collect();
+}
+
+/// The shield example with two more models, one of them a single voxel
+fn shield_with_models() -> dot_vox::DotVoxData
+{
+    let mut in_data = voxport::load("examples/shield.vox").unwrap();
+    let voxels = in_data.models[0].voxels[..10].to_vec();
+    in_data.models.push(dot_vox::Model {
+        size: in_data.models[0].size,
+        voxels
+    });
+    in_data.models.push(dot_vox::Model {
+        size: dot_vox::Size { x: 1, y: 1, z: 1 },
+        voxels: vec![dot_vox::Voxel { x: 0, y: 0, z: 0, i: 3 }]
+    });
+    return in_data;
+}
+
+#[test]
+fn every_model_gets_a_mesh_under_the_scene()
+{
+    let in_data = shield_with_models();
+    let mut json: Vec<u8> = Vec::new();
+    let mut bin: Vec<u8> = Vec::new();
+    voxport::convert_vox_gltf(&in_data, &Scene::from_models(&in_data), &MeshOptions::default(), &mut json, &mut bin, "models.bin").unwrap();
+    let document: Value = serde_json::from_slice(&json).unwrap();
+
+    assert_eq!(document["asset"]["version"], "2.0");
+    assert_eq!(index(&document["buffers"][0]["byteLength"]), bin.len());
+    assert_eq!(document["buffers"][0]["uri"], "models.bin");
+    assert_eq!(document["meshes"].as_array().unwrap().len(), in_data.models.len());
+
+    // Every mesh hangs off the node tree of the default scene exactly once
+    let nodes = document["nodes"].as_array().unwrap();
+    let mut meshes: Vec<usize> = Vec::new();
+    let mut stack: Vec<usize> = document["scenes"][index(&document["scene"])]["nodes"].as_array().unwrap().iter().map(index).collect();
+    let mut seen: Vec<bool> = vec![false; nodes.len()];
+    while let Some(node) = stack.pop()
+    {
+        assert!(!seen[node], "node {} has more than one parent", node);
+        seen[node] = true;
+        if let Some(mesh) = nodes[node].get("mesh")
+        {
+            meshes.push(index(mesh));
+        }
+        if let Some(children) = nodes[node].get("children")
+        {
+            stack.extend(children.as_array().unwrap().iter().map(index));
+        }
+    }
+    meshes.sort_unstable();
+    assert_eq!(meshes, (0..in_data.models.len()).collect::<Vec<_>>());
+
+    for mesh in document["meshes"].as_array().unwrap()
+    {
+        for primitive in mesh["primitives"].as_array().unwrap()
+        {
+            let attributes = &primitive["attributes"];
+            let count = index(&document["accessors"][index(&attributes["POSITION"])]["count"]);
+            for attribute in ["NORMAL", "COLOR_0"].iter()
+            {
+                assert_eq!(index(&document["accessors"][index(&attributes[*attribute])]["count"]), count);
+            }
+            let indices = read_accessor(&document, &bin, index(&primitive["indices"]));
+            assert_eq!(indices.len() % 3, 0);
+            assert!(indices.iter().all(|vertex| (*vertex as usize) < count));
+        }
+    }
+}
+
+#[test]
+fn vertex_colors_come_from_the_palette()
+{
+    let (in_data, scene) = voxport::load_scene("examples/shield.vox").unwrap();
+    let mut json: Vec<u8> = Vec::new();
+    let mut bin: Vec<u8> = Vec::new();
+    voxport::convert_vox_gltf(&in_data, &scene, &MeshOptions::default(), &mut json, &mut bin, "shield.bin").unwrap();
+    let document: Value = serde_json::from_slice(&json).unwrap();
+
+    let palette: Vec<[f32; 4]> = voxport::used_palette_indices(&in_data).iter()
+        .map(|index| Color::from_palette(&in_data.palette, *index).to_linear())
+        .collect();
+    let primitive = &document["meshes"][0]["primitives"][0];
+    let colors = read_accessor(&document, &bin, index(&primitive["attributes"]["COLOR_0"]));
+    let mut found: Vec<bool> = vec![false; palette.len()];
+    for color in colors.chunks(3)
+    {
+        let entry = palette.iter()
+            .position(|entry| (0..3).all(|channel| (entry[channel] as f64 - color[channel]).abs() < 1e-6))
+            .unwrap_or_else(|| panic!("{:?} is not a used palette color", color));
+        found[entry] = true;
+    }
+    assert!(found.iter().all(|found| *found));
+
+    let normals = read_accessor(&document, &bin, index(&primitive["attributes"]["NORMAL"]));
+    for normal in normals.chunks(3)
+    {
+        assert_eq!(normal.iter().map(|value| value.abs()).sum::<f64>(), 1.0, "{:?} is not an axis", normal);
+    }
+}
+
+#[test]
+fn glb_holds_the_document_and_buffer()
+{
+    let in_data = shield_with_models();
+    let scene = Scene::from_models(&in_data);
+    let mut glb: Vec<u8> = Vec::new();
+    voxport::convert_vox_glb(&in_data, &scene, &MeshOptions::default(), &mut glb).unwrap();
+    let word = |offset: usize| u32::from_le_bytes(glb[offset..offset + 4].try_into().unwrap()) as usize;
+
+    assert_eq!(&glb[0..4], b"glTF");
+    assert_eq!(word(4), 2);
+    assert_eq!(word(8), glb.len());
+
+    let json_length = word(12);
+    assert_eq!(&glb[16..20], b"JSON");
+    assert_eq!(json_length % 4, 0);
+    let document: Value = serde_json::from_slice(&glb[20..20 + json_length]).unwrap();
+
+    let bin_start = 20 + json_length;
+    let bin_length = word(bin_start);
+    assert_eq!(&glb[bin_start + 4..bin_start + 8], b"BIN\0");
+    assert_eq!(bin_start + 8 + bin_length, glb.len());
+
+    // The buffer is the BIN chunk, so it has no uri
+    let buffer = &document["buffers"][0];
+    assert!(buffer.get("uri").is_none());
+    assert!(index(&buffer["byteLength"]) <= bin_length);
+    assert_eq!(document["meshes"].as_array().unwrap().len(), in_data.models.len());
+
+    // Same content as the separate files
+    let mut json: Vec<u8> = Vec::new();
+    let mut bin: Vec<u8> = Vec::new();
+    voxport::convert_vox_gltf(&in_data, &scene, &MeshOptions::default(), &mut json, &mut bin, "models.bin").unwrap();
+    assert_eq!(&glb[bin_start + 8..bin_start + 8 + bin.len()], &bin[..]);
+}
@@ -0,0 +1,93 @@
+#![allow(clippy::needless_return)]
+
+extern crate dot_vox;
+extern crate voxport;
+
+use std::collections::BTreeMap;
+
+use voxport::{MeshOptions, Mesher, Triangle};
+
+/// Area of `triangles` per facing, plane and palette index. The palette index
+/// is left out if `colors` isn't set.
+fn areas(triangles: &[Triangle], colors: bool) -> BTreeMap<(i32, i32, i32, i32, u8), f32>
+{
+    let mut areas: BTreeMap<(i32, i32, i32, i32, u8), f32> = BTreeMap::new();
+    for triangle in triangles
+    {
+        let (a, b, c) = (triangle.a, triangle.b, triangle.c);
+        let u = [b.x - a.x, b.y - a.y, b.z - a.z];
+        let v = [c.x - a.x, c.y - a.y, c.z - a.z];
+        let cross = [u[1] * v[2] - u[2] * v[1], u[2] * v[0] - u[0] * v[2], u[0] * v[1] - u[1] * v[0]];
+        let area = (cross[0] * cross[0] + cross[1] * cross[1] + cross[2] * cross[2]).sqrt() / 2.0;
+
+        let normal = triangle.normal;
+        let plane = a.x * normal.x + a.y * normal.y + a.z * normal.z;
+        let key = (
+            normal.x as i32,
+            normal.y as i32,
+            normal.z as i32,
+            plane.round() as i32,
+            if colors { triangle.color_index } else { 0 }
+        );
+        *areas.entry(key).or_insert(0.0) += area;
+    }
+    return areas;
+}
+
+fn mesh(model: &dot_vox::Model, mesher: Mesher) -> Vec<Triangle>
+{
+    let options = MeshOptions {
+        mesher,
+        ..MeshOptions::default()
+    };
+    return voxport::convert_model(model, &options).unwrap();
+}
+
+#[test]
+fn greedy_covers_the_naive_surface()
+{
+    for path in ["examples/shield.vox", "examples/column.vox"].iter()
+    {
+        let in_data = voxport::load(path).unwrap();
+        for model in &in_data.models
+        {
+            let naive = mesh(model, Mesher::Naive);
+            for merge_colors in [false, true].iter()
+            {
+                let greedy = mesh(model, Mesher::Greedy { merge_colors: *merge_colors });
+                assert!(greedy.len() <= naive.len());
+
+                let expected = areas(&naive, !merge_colors);
+                let actual = areas(&greedy, !merge_colors);
+                assert_eq!(expected.keys().collect::<Vec<_>>(), actual.keys().collect::<Vec<_>>(), "{}", path);
+                for (key, area) in &expected
+                {
+                    assert!((area - actual[key]).abs() < 1e-3, "{}: area {} of {:?} became {}", path, area, key, actual[key]);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn solid_block_becomes_one_quad_per_side()
+{
+    let mut voxels: Vec<dot_vox::Voxel> = Vec::new();
+    for z in 0..4
+    {
+        for y in 0..4
+        {
+            for x in 0..4
+            {
+                voxels.push(dot_vox::Voxel { x, y, z, i: 7 });
+            }
+        }
+    }
+    let model = dot_vox::Model {
+        size: dot_vox::Size { x: 4, y: 4, z: 4 },
+        voxels
+    };
+
+    assert_eq!(mesh(&model, Mesher::Naive).len(), 6 * 16 * 2);
+    assert_eq!(mesh(&model, Mesher::Greedy { merge_colors: false }).len(), 6 * 2);
+}
@@ -0,0 +1,108 @@
+#![allow(clippy::needless_return)]
+
+extern crate dot_vox;
+extern crate voxport;
+
+use voxport::hollow::hollow_voxels;
+use voxport::mesh::{get_voxel_idx, reorder_voxels};
+use voxport::{Drain, Hollow, MeshOptions, Mesher, Triangle};
+
+/// A solid cube of 7x7x7 voxels
+fn cube() -> dot_vox::Model
+{
+    let mut voxels: Vec<dot_vox::Voxel> = Vec::new();
+    for z in 0..7
+    {
+        for y in 0..7
+        {
+            for x in 0..7
+            {
+                voxels.push(dot_vox::Voxel { x, y, z, i: 1 });
+            }
+        }
+    }
+    return dot_vox::Model {
+        size: dot_vox::Size { x: 7, y: 7, z: 7 },
+        voxels
+    };
+}
+
+fn hollow(wall: u32, drains: Vec<Drain>) -> Vec<Triangle>
+{
+    let options = MeshOptions {
+        mesher: Mesher::Naive,
+        hollow: Some(Hollow { wall, drains }),
+        ..MeshOptions::default()
+    };
+    return voxport::convert_model(&cube(), &options).unwrap();
+}
+
+/// Number of faces pointing toward the center of the cube, which make up the inner shell
+fn inner_faces(triangles: &[Triangle]) -> usize
+{
+    return triangles.iter()
+        .filter(|triangle| {
+            let center = [
+                (triangle.a.x + triangle.b.x + triangle.c.x) / 3.0 - 3.5,
+                (triangle.a.y + triangle.b.y + triangle.c.y) / 3.0 - 3.5,
+                (triangle.a.z + triangle.b.z + triangle.c.z) / 3.0 - 3.5
+            ];
+            let normal = triangle.normal;
+            return center[0] * normal.x + center[1] * normal.y + center[2] * normal.z < 0.0;
+        })
+        .count() / 2;
+}
+
+#[test]
+fn walls_keep_their_thickness()
+{
+    // Two voxel walls leave a 3x3x3 cavity
+    let triangles = hollow(2, Vec::new());
+    assert_eq!(triangles.len() / 2, 6 * 49 + 6 * 9);
+    assert_eq!(inner_faces(&triangles), 6 * 9);
+
+    // Walls meeting in the middle leave the cube solid
+    assert_eq!(hollow(4, Vec::new()).len() / 2, 6 * 49);
+}
+
+#[test]
+fn drain_holes_open_the_cavity_downwards()
+{
+    // The hole through the floor of two voxels trades a face of each shell
+    // for the four sides of both voxels
+    let expected = 6 * 49 + 6 * 9 - 2 + 2 * 4;
+    let column = hollow(2, vec![Drain::Column(3, 3)]);
+    assert_eq!(column.len() / 2, expected);
+    assert!(column.iter().all(|triangle| !(triangle.normal.z < 0.0 && triangle.a.x == 3.0 && triangle.a.y == 3.0 && triangle.a.z == 0.0)));
+
+    assert_eq!(hollow(2, vec![Drain::Lowest]).len() / 2, expected);
+
+    // Columns missing the cavity change nothing
+    assert_eq!(hollow(2, vec![Drain::Column(0, 0)]).len() / 2, 6 * 49 + 6 * 9);
+}
+
+#[test]
+fn drain_holes_stop_below_the_wall()
+{
+    // The cube stands on a slab of two voxels, with an empty layer between them
+    let mut voxels: Vec<dot_vox::Voxel> = cube().voxels.iter()
+        .map(|voxel| dot_vox::Voxel { z: voxel.z + 3, ..*voxel })
+        .collect();
+    for z in 0..2
+    {
+        for y in 0..7
+        {
+            for x in 0..7
+            {
+                voxels.push(dot_vox::Voxel { x, y, z, i: 2 });
+            }
+        }
+    }
+    let size = dot_vox::Size { x: 7, y: 7, z: 10 };
+    let mut grid = reorder_voxels(&voxels, &size).unwrap();
+    hollow_voxels(&mut grid, &size, &Hollow { wall: 2, drains: vec![Drain::Column(3, 3)] });
+
+    let filled = |z: u8| grid[get_voxel_idx(&dot_vox::Voxel { x: 3, y: 3, z, i: 0 }, &size)].is_some();
+    assert_eq!((0..10).map(filled).collect::<Vec<_>>(), vec![true, true, false, false, false, false, false, false, true, true]);
+    assert_eq!(grid.iter().filter(|cell| cell.map(|voxel| voxel.i == 2).unwrap_or(false)).count(), 2 * 49);
+}
@@ -0,0 +1,151 @@
+#![allow(clippy::needless_return)]
+
+extern crate dot_vox;
+extern crate voxport;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use voxport::{MeshOptions, Mesher, Triangle, Vertex};
+
+type Key = (u32, u32, u32);
+
+fn key(vertex: Vertex) -> Key
+{
+    return (vertex.x.to_bits(), vertex.y.to_bits(), vertex.z.to_bits());
+}
+
+fn mesh(size: u32, filled: &[[u8; 3]], mesher: Mesher) -> Vec<Triangle>
+{
+    let model = dot_vox::Model {
+        size: dot_vox::Size { x: size, y: size, z: size },
+        voxels: filled.iter().map(|cell| dot_vox::Voxel { x: cell[0], y: cell[1], z: cell[2], i: 1 }).collect()
+    };
+    let options = MeshOptions {
+        mesher,
+        ..MeshOptions::default()
+    };
+    return voxport::convert_model(&model, &options).unwrap();
+}
+
+/// Describes why `triangles` isn't a closed 2-manifold surface, checking that
+/// every edge is used by exactly two triangles, once in each direction, and
+/// that the triangles around every vertex form a single fan
+fn manifold_error(triangles: &[Triangle]) -> Option<String>
+{
+    let mut directed: BTreeMap<(Key, Key), usize> = BTreeMap::new();
+    let mut around: BTreeMap<Key, Vec<(Key, Key)>> = BTreeMap::new();
+    for triangle in triangles
+    {
+        let corners = [key(triangle.a), key(triangle.b), key(triangle.c)];
+        for i in 0..3
+        {
+            let (start, end, opposite) = (corners[i], corners[(i + 1) % 3], corners[(i + 2) % 3]);
+            *directed.entry((start, end)).or_default() += 1;
+            around.entry(opposite).or_default().push((start, end));
+        }
+    }
+
+    for ((start, end), count) in &directed
+    {
+        let reverse = directed.get(&(*end, *start)).copied().unwrap_or(0);
+        if *count != 1 || reverse != 1
+        {
+            return Some(format!("edge {:?} -> {:?} is used by {} + {} triangles", start, end, count, reverse));
+        }
+    }
+
+    // The far edges of the triangles around a vertex chain into one loop
+    for (vertex, links) in &around
+    {
+        let next: BTreeMap<Key, Key> = links.iter().copied().collect();
+        let mut visited: BTreeSet<Key> = BTreeSet::new();
+        let mut current = links[0].0;
+        while visited.insert(current)
+        {
+            current = next[&current];
+        }
+        if visited.len() != links.len()
+        {
+            return Some(format!("vertex {:?} joins more than one fan", vertex));
+        }
+    }
+    return None;
+}
+
+fn assert_manifold(size: u32, filled: &[[u8; 3]])
+{
+    let triangles = mesh(size, filled, Mesher::Manifold);
+    if let Some(error) = manifold_error(&triangles)
+    {
+        panic!("{} in the mesh of {:?}", error, filled);
+    }
+}
+
+/// Cells of a cube of `size` whose bits are set in `mask`, x first
+fn cells(size: u8, mask: u64) -> Vec<[u8; 3]>
+{
+    let mut filled: Vec<[u8; 3]> = Vec::new();
+    for z in 0..size
+    {
+        for y in 0..size
+        {
+            for x in 0..size
+            {
+                let bit = x as u64 + y as u64 * size as u64 + z as u64 * size as u64 * size as u64;
+                if mask >> bit & 1 == 1
+                {
+                    filled.push([x, y, z]);
+                }
+            }
+        }
+    }
+    return filled;
+}
+
+#[test]
+fn voxels_touching_at_edges_and_corners_are_split()
+{
+    let edge: &[[u8; 3]] = &[[0, 0, 0], [1, 1, 0]];
+    let corner: &[[u8; 3]] = &[[0, 0, 0], [1, 1, 1]];
+    for filled in [edge, corner].iter()
+    {
+        assert!(manifold_error(&mesh(2, filled, Mesher::Naive)).is_some());
+        assert_manifold(2, filled);
+    }
+}
+
+#[test]
+fn every_cell_pattern_of_a_cube_is_manifold()
+{
+    for mask in 1..1u64 << 8
+    {
+        assert_manifold(2, &cells(2, mask));
+    }
+
+    // A fixed sequence of pseudo-random patterns, mixing every kind of contact
+    let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+    for _ in 0..1000
+    {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        assert_manifold(3, &cells(3, state & ((1 << 27) - 1)));
+    }
+}
+
+#[test]
+fn split_vertices_stay_next_to_the_voxel_corners()
+{
+    // Every edge of every voxel is shared by two voxels touching only there
+    let filled = cells(3, 0b101_010_101_010_101_010_101_010_101);
+    for triangle in mesh(3, &filled, Mesher::Manifold)
+    {
+        for vertex in [triangle.a, triangle.b, triangle.c].iter()
+        {
+            // Corners, edge midpoints and face centers all lie on the half voxel grid
+            let snap = |value: f32| value - (value * 2.0).round() / 2.0;
+            let distance = (snap(vertex.x).powi(2) + snap(vertex.y).powi(2) + snap(vertex.z).powi(2)).sqrt();
+            assert!(distance <= voxport::manifold::SPLIT_OFFSET * 1.001, "{:?} moved by {}", vertex, distance);
+        }
+    }
+}
@@ -0,0 +1,82 @@
+#![allow(clippy::needless_return)]
+
+extern crate dot_vox;
+extern crate serde_json;
+extern crate voxport;
+
+use std::collections::HashMap;
+
+use voxport::{MeshOptions, Scene, VoxMaterial};
+
+fn properties(pairs: &[(&str, &str)]) -> HashMap<String, String>
+{
+    return pairs.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect();
+}
+
+/// The shield example with a metal, a glowing and a glass palette index.
+/// `MATL` ids are one above the palette index they belong to.
+fn shield_with_materials() -> dot_vox::DotVoxData
+{
+    let mut in_data = voxport::load("examples/shield.vox").unwrap();
+    let overrides = [
+        (14, properties(&[("_type", "_metal"), ("_metal", "0.8"), ("_rough", "0.2")])),
+        (149, properties(&[("_type", "_emit"), ("_emit", "0.5")])),
+        (151, properties(&[("_type", "_glass"), ("_trans", "0.6"), ("_ior", "0.45")]))
+    ];
+    for (index, properties) in overrides.iter()
+    {
+        let material = in_data.materials.iter_mut().find(|material| material.id == index + 1).unwrap();
+        material.properties = properties.clone();
+    }
+    return in_data;
+}
+
+#[test]
+fn properties_translate_to_pbr_factors()
+{
+    let metal = VoxMaterial::from_properties(&properties(&[("_type", "_metal"), ("_metal", "0.7"), ("_rough", "0.25")]));
+    assert_eq!((metal.metallic, metal.roughness), (0.7, 0.25));
+
+    // Older files give the strength as a weight
+    let metal = VoxMaterial::from_properties(&properties(&[("_type", "_metal"), ("_weight", "0.5"), ("_plastic", "1")]));
+    assert_eq!(metal.metallic, 0.5);
+
+    let glass = VoxMaterial::from_properties(&properties(&[("_type", "_glass"), ("_alpha", "0.4"), ("_ior", "0.3")]));
+    assert_eq!(glass.transmission, 0.4);
+    assert!((glass.ior - 1.3).abs() < 1e-6);
+
+    let blend = VoxMaterial::from_properties(&properties(&[("_type", "_blend"), ("_trans", "0.25"), ("_emit", "2")]));
+    assert_eq!((blend.opacity, blend.emission), (0.75, 1.0));
+
+    let diffuse = VoxMaterial::from_properties(&properties(&[("_type", "_diffuse"), ("_rough", "0.1"), ("_ior", "0.3")]));
+    assert!(diffuse.is_default());
+}
+
+#[test]
+fn gltf_gets_a_material_per_special_palette_index()
+{
+    let in_data = shield_with_materials();
+    let scene = Scene::from_models(&in_data);
+    let mut json: Vec<u8> = Vec::new();
+    let mut bin: Vec<u8> = Vec::new();
+    voxport::convert_vox_gltf(&in_data, &scene, &MeshOptions::default(), &mut json, &mut bin, "out.bin").unwrap();
+    let document: serde_json::Value = serde_json::from_slice(&json).unwrap();
+
+    let materials = document["materials"].as_array().unwrap();
+    let material = |name: &str| -> &serde_json::Value {
+        return materials.iter().find(|material| material["name"] == name).unwrap_or_else(|| panic!("no material {}", name));
+    };
+    assert_eq!(materials.len(), 4);
+    assert_eq!(materials[0]["name"], "Voxel");
+    assert_eq!(material("palette_14")["pbrMetallicRoughness"]["metallicFactor"].as_f64().unwrap() as f32, 0.8);
+    assert!(material("palette_149")["emissiveFactor"].as_array().unwrap().iter().any(|factor| factor.as_f64().unwrap() > 0.0));
+    let transmission = &material("palette_151")["extensions"]["KHR_materials_transmission"]["transmissionFactor"];
+    assert_eq!(transmission.as_f64().unwrap() as f32, 0.6);
+    assert_eq!(document["extensionsUsed"], serde_json::json!(["KHR_materials_ior", "KHR_materials_transmission"]));
+
+    // Palette index 159 keeps the shared material
+    let primitives = document["meshes"][0]["primitives"].as_array().unwrap();
+    let mut used: Vec<u64> = primitives.iter().map(|primitive| primitive["material"].as_u64().unwrap()).collect();
+    used.sort_unstable();
+    assert_eq!(used, vec![0, 1, 2, 3]);
+}
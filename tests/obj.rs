@@ -0,0 +1,79 @@
+#![allow(clippy::needless_return)]
+
+extern crate voxport;
+
+use std::collections::HashSet;
+
+use voxport::{MeshOptions, Mesher};
+
+fn export(path: &str, options: &MeshOptions) -> (String, String)
+{
+    let (in_data, scene) = voxport::load_scene(path).unwrap();
+    let mut obj: Vec<u8> = Vec::new();
+    let mut mtl: Vec<u8> = Vec::new();
+    voxport::convert_vox_obj(&in_data, &scene, options, &mut obj, &mut mtl, "model.mtl").unwrap();
+    return (String::from_utf8(obj).unwrap(), String::from_utf8(mtl).unwrap());
+}
+
+fn lines<'a>(text: &'a str, keyword: &'a str) -> impl Iterator<Item = Vec<&'a str>> + 'a
+{
+    return text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<&str>>())
+        .filter(move |words| words.first() == Some(&keyword))
+        .map(|words| words[1..].to_vec());
+}
+
+#[test]
+fn faces_come_out_as_quads()
+{
+    for path in ["examples/shield.vox", "examples/column.vox"].iter()
+    {
+        for mesher in [Mesher::Naive, Mesher::Greedy { merge_colors: false }].iter()
+        {
+            let options = MeshOptions {
+                mesher: *mesher,
+                ..MeshOptions::default()
+            };
+            let (obj, _) = export(path, &options);
+            let (in_data, scene) = voxport::load_scene(path).unwrap();
+            let triangles = voxport::convert_vox(&in_data, &scene, &options).unwrap();
+
+            let faces: Vec<Vec<&str>> = lines(&obj, "f").collect();
+            assert!(faces.iter().all(|corners| corners.len() == 4), "{} has faces that aren't quads", path);
+            assert_eq!(faces.len() * 2, triangles.len());
+
+            let vertex_count = lines(&obj, "v").count();
+            let normal_count = lines(&obj, "vn").count();
+            for corner in faces.iter().flatten()
+            {
+                let indices: Vec<&str> = corner.split('/').collect();
+                let vertex: usize = indices[0].parse().unwrap();
+                let normal: usize = indices[2].parse().unwrap();
+                assert!(vertex >= 1 && vertex <= vertex_count);
+                assert!(normal >= 1 && normal <= normal_count);
+            }
+        }
+    }
+}
+
+#[test]
+fn materials_group_the_faces_of_each_object()
+{
+    let (obj, mtl) = export("examples/shield.vox", &MeshOptions::default());
+    let (in_data, scene) = voxport::load_scene("examples/shield.vox").unwrap();
+    assert!(obj.lines().any(|line| line == "mtllib model.mtl"));
+    assert_eq!(lines(&obj, "o").count(), scene.instances(&in_data.models).len());
+
+    let materials: HashSet<String> = lines(&mtl, "newmtl").map(|words| words[0].to_string()).collect();
+    let used: HashSet<String> = voxport::used_palette_indices(&in_data).iter().map(|index| format!("palette_{}", index)).collect();
+    assert_eq!(materials, used);
+
+    // Each material is switched to once per object
+    for object in obj.split("\no ").skip(1)
+    {
+        let switches: Vec<&str> = lines(object, "usemtl").map(|words| words[0]).collect();
+        let unique: HashSet<&&str> = switches.iter().collect();
+        assert_eq!(unique.len(), switches.len());
+        assert!(switches.iter().all(|name| materials.contains(*name)));
+    }
+}
@@ -0,0 +1,126 @@
+#![allow(clippy::needless_return)]
+
+extern crate png;
+extern crate voxport;
+
+use std::collections::BTreeMap;
+
+use voxport::{Color, PaletteFormat, TextureLayout};
+
+fn export(format: PaletteFormat, used_only: bool) -> Vec<u8>
+{
+    let in_data = voxport::load("examples/shield.vox").unwrap();
+    let mut bytes: Vec<u8> = Vec::new();
+    voxport::export_palette(&in_data, format, used_only, &mut bytes).unwrap();
+    return bytes;
+}
+
+#[test]
+fn text_formats_list_every_color()
+{
+    let jasc = String::from_utf8(export(PaletteFormat::Jasc, false)).unwrap();
+    let lines: Vec<&str> = jasc.lines().collect();
+    assert_eq!(&lines[..3], &["JASC-PAL", "0100", "256"]);
+    assert_eq!(lines.len(), 3 + 256);
+
+    let gimp = String::from_utf8(export(PaletteFormat::Gimp, true)).unwrap();
+    assert!(gimp.starts_with("GIMP Palette\n"));
+    assert_eq!(gimp.lines().filter(|line| line.contains("palette_")).count(), 4);
+
+    let hex = String::from_utf8(export(PaletteFormat::Hex, true)).unwrap();
+    assert_eq!(hex.lines().collect::<Vec<_>>(), vec!["d0104c", "1b813e", "36563c", "00896c"]);
+}
+
+#[test]
+fn used_colors_shrink_binary_formats()
+{
+    assert_eq!(export(PaletteFormat::Act, false).len(), 768);
+    let act = export(PaletteFormat::Act, true);
+    assert_eq!(act.len(), 772);
+    assert_eq!(&act[..3], &[0xd0, 0x10, 0x4c]);
+    // Number of colors and no transparent index
+    assert_eq!(&act[768..], &[0, 4, 0xFF, 0xFF]);
+
+    let png = export(PaletteFormat::Png, true);
+    let info = png::Decoder::new(png.as_slice()).read_info().unwrap().info().clone();
+    assert_eq!((info.width, info.height), (4 * voxport::palette::SWATCH_SIZE, voxport::palette::SWATCH_SIZE));
+}
+
+#[test]
+fn exported_palettes_read_back()
+{
+    let in_data = voxport::load("examples/shield.vox").unwrap();
+    let palette: Vec<Color> = in_data.palette.iter().map(|entry| Color::from(*entry)).collect();
+    for format in [PaletteFormat::Jasc, PaletteFormat::Gimp].iter()
+    {
+        let colors = voxport::import_palette(*format, export(*format, false).as_slice()).unwrap();
+        // Text palettes hold no alpha
+        let opaque: Vec<Color> = palette.iter().map(|color| Color { a: 255, ..*color }).collect();
+        assert_eq!(colors, opaque, "{:?}", format);
+    }
+
+    for layout in [TextureLayout::Grid, TextureLayout::Strip].iter()
+    {
+        let mut png: Vec<u8> = Vec::new();
+        voxport::export_palette_texture(&in_data, *layout, &mut png).unwrap();
+        assert_eq!(voxport::import_palette(PaletteFormat::Png, png.as_slice()).unwrap(), palette);
+    }
+
+    // Swatch strips give a color per swatch
+    let swatches = voxport::import_palette(PaletteFormat::Png, export(PaletteFormat::Png, false).as_slice()).unwrap();
+    assert_eq!(swatches, palette);
+    let used = voxport::import_palette(PaletteFormat::Png, export(PaletteFormat::Png, true).as_slice()).unwrap();
+    let used_palette: Vec<Color> = voxport::used_palette_indices(&in_data).iter().map(|index| palette[*index as usize]).collect();
+    assert_eq!(used, used_palette);
+
+    match voxport::import_palette(PaletteFormat::Jasc, "JASC-PAL\n0100\n2\n255 0 0\n".as_bytes())
+    {
+        Err(voxport::VoxportError::InvalidInput(_)) => {},
+        other => panic!("expected an invalid input error, got {:?}", other)
+    }
+}
+
+/// Encodes RGBA `colors`, `width` pixels wide, as a PNG
+fn encode_png(colors: &[Color], width: u32) -> Vec<u8>
+{
+    let mut bytes: Vec<u8> = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, width, colors.len() as u32 / width);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let pixels: Vec<u8> = colors.iter().flat_map(|color| vec! { color.r, color.g, color.b, color.a }).collect();
+    encoder.write_header().unwrap().write_image_data(&pixels).unwrap();
+    return bytes;
+}
+
+#[test]
+fn only_uniform_swatches_read_as_swatch_strips()
+{
+    let gray = |i: usize| Color { r: i as u8, g: i as u8, b: i as u8, a: 255 };
+
+    // 16x2 palettes hold a color per pixel, not two swatches
+    let colors: Vec<Color> = (0..32).map(gray).collect();
+    assert_eq!(voxport::import_palette(PaletteFormat::Png, encode_png(&colors, 16).as_slice()).unwrap(), colors);
+
+    // So do 16 pixel high images whose squares aren't one color
+    let colors: Vec<Color> = (0..32 * 16).map(gray).collect();
+    assert_eq!(voxport::import_palette(PaletteFormat::Png, encode_png(&colors, 32).as_slice()).unwrap(), colors);
+
+    let swatches: Vec<Color> = (0..32 * 16).map(|i| gray(i % 32 / 16)).collect();
+    assert_eq!(voxport::import_palette(PaletteFormat::Png, encode_png(&swatches, 32).as_slice()).unwrap(), vec! { gray(0), gray(1) });
+}
+
+#[test]
+fn palette_and_remap_recolor_voxels()
+{
+    let mut in_data = voxport::load("examples/shield.vox").unwrap();
+    let red = Color { r: 255, g: 0, b: 0, a: 255 };
+    voxport::apply_palette(&mut in_data, &[red; 15]);
+    assert_eq!(Color::from_palette(&in_data.palette, 14), red);
+    assert_eq!(in_data.palette.len(), 256);
+
+    let mut remap = BTreeMap::new();
+    remap.insert(151, 14);
+    voxport::remap_palette_indices(&mut in_data, &remap);
+    let used: Vec<u8> = voxport::used_palette_indices(&in_data).into_iter().collect();
+    assert_eq!(used, vec![14, 149, 159]);
+}
@@ -0,0 +1,137 @@
+#![allow(clippy::needless_return)]
+
+extern crate voxport;
+
+use std::convert::TryInto;
+
+use voxport::{Color, MeshOptions, PlyFormat};
+
+/// The vertices and faces of a PLY file, as written by voxport
+struct Ply
+{
+    vertices: Vec<([f32; 3], [u8; 4])>,
+    faces: Vec<[usize; 3]>
+}
+
+fn export(format: PlyFormat) -> Vec<u8>
+{
+    let (in_data, scene) = voxport::load_scene("examples/shield.vox").unwrap();
+    let mut bytes: Vec<u8> = Vec::new();
+    voxport::convert_vox_ply(&in_data, &scene, &MeshOptions::default(), format, &mut bytes).unwrap();
+    return bytes;
+}
+
+/// Splits off the header, returning its format, element counts and length in bytes
+fn parse_header(bytes: &[u8]) -> (String, usize, usize, usize)
+{
+    let end = b"end_header\n";
+    let length = bytes.windows(end.len()).position(|window| window == end).expect("no end_header") + end.len();
+    let header = std::str::from_utf8(&bytes[..length]).unwrap();
+    let lines: Vec<&str> = header.lines().collect();
+    assert_eq!(lines[0], "ply");
+
+    let mut format = String::new();
+    let mut elements: Vec<(&str, usize)> = Vec::new();
+    for line in &lines
+    {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[0]
+        {
+            "format" => {
+                assert_eq!(words[2], "1.0");
+                format = words[1].to_string();
+            },
+            "element" => {
+                elements.push((words[1], words[2].parse().unwrap()));
+            },
+            _ => {}
+        }
+    }
+    assert_eq!(elements.iter().map(|element| element.0).collect::<Vec<_>>(), vec!["vertex", "face"]);
+    return (format, elements[0].1, elements[1].1, length);
+}
+
+fn parse_ascii(bytes: &[u8]) -> Ply
+{
+    let (format, vertex_count, face_count, length) = parse_header(bytes);
+    assert_eq!(format, "ascii");
+
+    let body = std::str::from_utf8(&bytes[length..]).unwrap();
+    let lines: Vec<Vec<&str>> = body.lines().map(|line| line.split_whitespace().collect()).collect();
+    assert_eq!(lines.len(), vertex_count + face_count);
+
+    let vertices = lines[..vertex_count].iter()
+        .map(|words| {
+            assert_eq!(words.len(), 7);
+            let position = [words[0].parse().unwrap(), words[1].parse().unwrap(), words[2].parse().unwrap()];
+            let color = [words[3].parse().unwrap(), words[4].parse().unwrap(), words[5].parse().unwrap(), words[6].parse().unwrap()];
+            (position, color)
+        })
+        .collect();
+    let faces = lines[vertex_count..].iter()
+        .map(|words| {
+            assert_eq!(words[0], "3");
+            [words[1].parse().unwrap(), words[2].parse().unwrap(), words[3].parse().unwrap()]
+        })
+        .collect();
+    return Ply { vertices, faces };
+}
+
+fn parse_binary(bytes: &[u8]) -> Ply
+{
+    let (format, vertex_count, face_count, length) = parse_header(bytes);
+    assert_eq!(format, "binary_little_endian");
+
+    // Three floats and four bytes per vertex, a count byte and three ints per face
+    let body = &bytes[length..];
+    assert_eq!(body.len(), vertex_count * 16 + face_count * 13);
+
+    let float = |at: usize| f32::from_le_bytes(body[at..at + 4].try_into().unwrap());
+    let int = |at: usize| i32::from_le_bytes(body[at..at + 4].try_into().unwrap()) as usize;
+    let vertices = (0..vertex_count)
+        .map(|vertex| {
+            let at = vertex * 16;
+            ([float(at), float(at + 4), float(at + 8)], body[at + 12..at + 16].try_into().unwrap())
+        })
+        .collect();
+    let faces = (0..face_count)
+        .map(|face| {
+            let at = vertex_count * 16 + face * 13;
+            assert_eq!(body[at], 3);
+            [int(at + 1), int(at + 5), int(at + 9)]
+        })
+        .collect();
+    return Ply { vertices, faces };
+}
+
+#[test]
+fn faces_read_back_as_the_meshed_triangles()
+{
+    let (in_data, scene) = voxport::load_scene("examples/shield.vox").unwrap();
+    let triangles = voxport::convert_vox(&in_data, &scene, &MeshOptions::default()).unwrap();
+
+    for ply in [parse_ascii(&export(PlyFormat::Ascii)), parse_binary(&export(PlyFormat::BinaryLittleEndian))].iter()
+    {
+        assert_eq!(ply.faces.len(), triangles.len());
+        assert!(ply.vertices.len() < triangles.len() * 3);
+        for (face, triangle) in ply.faces.iter().zip(&triangles)
+        {
+            let color = Color::from_palette(&in_data.palette, triangle.color_index);
+            for (corner, vertex) in face.iter().zip([triangle.a, triangle.b, triangle.c].iter())
+            {
+                let (position, rgba) = ply.vertices[*corner];
+                assert_eq!(position, [vertex.x, vertex.y, vertex.z]);
+                assert_eq!(rgba, [color.r, color.g, color.b, color.a]);
+            }
+        }
+    }
+}
+
+#[test]
+fn ascii_and_binary_hold_the_same_mesh()
+{
+    let ascii = parse_ascii(&export(PlyFormat::Ascii));
+    let binary = parse_binary(&export(PlyFormat::BinaryLittleEndian));
+    assert_eq!(ascii.vertices, binary.vertices);
+    assert_eq!(ascii.faces, binary.faces);
+}
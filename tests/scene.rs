@@ -0,0 +1,337 @@
+#![allow(clippy::needless_return)]
+
+extern crate voxport;
+
+use voxport::{Layer, MeshOptions, Pivot, Selection, SplitMode, Triangle};
+
+fn push_u32(bytes: &mut Vec<u8>, value: u32)
+{
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn push_dict(bytes: &mut Vec<u8>, pairs: &[(&str, &str)])
+{
+    push_u32(bytes, pairs.len() as u32);
+    for (key, value) in pairs
+    {
+        push_u32(bytes, key.len() as u32);
+        bytes.extend_from_slice(key.as_bytes());
+        push_u32(bytes, value.len() as u32);
+        bytes.extend_from_slice(value.as_bytes());
+    }
+}
+
+fn push_chunk(bytes: &mut Vec<u8>, id: &[u8; 4], content: &[u8])
+{
+    bytes.extend_from_slice(id);
+    push_u32(bytes, content.len() as u32);
+    push_u32(bytes, 0);
+    bytes.extend_from_slice(content);
+}
+
+fn transform_chunk(bytes: &mut Vec<u8>, id: u32, child: u32, attributes: &[(&str, &str)], frame: &[(&str, &str)])
+{
+    let mut content: Vec<u8> = Vec::new();
+    push_u32(&mut content, id);
+    push_dict(&mut content, attributes);
+    push_u32(&mut content, child);
+    push_u32(&mut content, u32::MAX); // Reserved
+    push_u32(&mut content, 0); // Layer
+    push_u32(&mut content, 1);
+    push_dict(&mut content, frame);
+    push_chunk(bytes, b"nTRN", &content);
+}
+
+fn group_chunk(bytes: &mut Vec<u8>, id: u32, children: &[u32])
+{
+    let mut content: Vec<u8> = Vec::new();
+    push_u32(&mut content, id);
+    push_dict(&mut content, &[]);
+    push_u32(&mut content, children.len() as u32);
+    for child in children
+    {
+        push_u32(&mut content, *child);
+    }
+    push_chunk(bytes, b"nGRP", &content);
+}
+
+/// A shape showing `models` in turn, listed back to front to check they are
+/// ordered by their frame index
+fn shape_chunk(bytes: &mut Vec<u8>, id: u32, models: &[u32])
+{
+    let mut content: Vec<u8> = Vec::new();
+    push_u32(&mut content, id);
+    push_dict(&mut content, &[]);
+    push_u32(&mut content, models.len() as u32);
+    for (frame, model) in models.iter().enumerate().rev()
+    {
+        push_u32(&mut content, *model);
+        push_dict(&mut content, &[("_f", &frame.to_string())]);
+    }
+    push_chunk(bytes, b"nSHP", &content);
+}
+
+/// A single voxel model and a 2x1x1 model, the first translated and the
+/// second placed with the packed rotation `rotation` under a node named "arm"
+fn scene_file(rotation: &str) -> Vec<u8>
+{
+    return build_file(rotation, &[0], &[1]);
+}
+
+fn build_file(rotation: &str, first: &[u32], second: &[u32]) -> Vec<u8>
+{
+    let mut chunks: Vec<u8> = Vec::new();
+    let models: [([u32; 3], &[[u8; 4]]); 2] = [
+        ([1, 1, 1], &[[0, 0, 0, 1]]),
+        ([2, 1, 1], &[[0, 0, 0, 2], [1, 0, 0, 2]])
+    ];
+    for (size, voxels) in models.iter()
+    {
+        let mut content: Vec<u8> = Vec::new();
+        for axis in size
+        {
+            push_u32(&mut content, *axis);
+        }
+        push_chunk(&mut chunks, b"SIZE", &content);
+
+        let mut content: Vec<u8> = Vec::new();
+        push_u32(&mut content, voxels.len() as u32);
+        for voxel in voxels.iter()
+        {
+            content.extend_from_slice(voxel);
+        }
+        push_chunk(&mut chunks, b"XYZI", &content);
+    }
+
+    transform_chunk(&mut chunks, 0, 1, &[], &[]);
+    group_chunk(&mut chunks, 1, &[2, 4]);
+    transform_chunk(&mut chunks, 2, 3, &[], &[("_t", "10 0 0")]);
+    shape_chunk(&mut chunks, 3, first);
+    transform_chunk(&mut chunks, 4, 5, &[("_name", "arm")], &[("_r", rotation), ("_t", "0 0 5")]);
+    shape_chunk(&mut chunks, 5, second);
+
+    let mut bytes: Vec<u8> = b"VOX ".to_vec();
+    push_u32(&mut bytes, 150);
+    bytes.extend_from_slice(b"MAIN");
+    push_u32(&mut bytes, 0);
+    push_u32(&mut bytes, chunks.len() as u32);
+    bytes.extend_from_slice(&chunks);
+    return bytes;
+}
+
+fn bounds(triangles: &[Triangle]) -> ([f32; 3], [f32; 3])
+{
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for triangle in triangles
+    {
+        for vertex in [triangle.a, triangle.b, triangle.c].iter()
+        {
+            for (axis, value) in [vertex.x, vertex.y, vertex.z].iter().enumerate()
+            {
+                min[axis] = min[axis].min(*value);
+                max[axis] = max[axis].max(*value);
+            }
+        }
+    }
+    return (min, max);
+}
+
+fn assert_winding_matches_normal(triangles: &[Triangle])
+{
+    for triangle in triangles
+    {
+        let u = [triangle.b.x - triangle.a.x, triangle.b.y - triangle.a.y, triangle.b.z - triangle.a.z];
+        let v = [triangle.c.x - triangle.a.x, triangle.c.y - triangle.a.y, triangle.c.z - triangle.a.z];
+        let cross = [u[1] * v[2] - u[2] * v[1], u[2] * v[0] - u[0] * v[2], u[0] * v[1] - u[1] * v[0]];
+        let dot = cross[0] * triangle.normal.x + cross[1] * triangle.normal.y + cross[2] * triangle.normal.z;
+        assert!(dot > 0.0, "{:?} winds against its normal", triangle);
+    }
+}
+
+#[test]
+fn transforms_place_models()
+{
+    // Quarter turn about z: the first row picks y and is negated (bit 4)
+    let (in_data, scene) = voxport::load_scene_bytes(&scene_file("17")).unwrap();
+    let instances = voxport::convert_instances(&in_data, &scene, &MeshOptions::default()).unwrap();
+    assert_eq!(instances.len(), 2);
+
+    let (first, triangles) = &instances[0];
+    assert_eq!(first.model, 0);
+    assert_eq!(bounds(triangles), ([10.0, 0.0, 0.0], [11.0, 1.0, 1.0]));
+
+    // Centered on its pivot the model spans x -1..1, which the turn takes to y
+    let (second, triangles) = &instances[1];
+    assert_eq!(second.model, 1);
+    assert_eq!(second.name, "arm");
+    assert_eq!(bounds(triangles), ([0.0, -1.0, 5.0], [1.0, 1.0, 6.0]));
+    assert_winding_matches_normal(triangles);
+}
+
+#[test]
+fn mirroring_keeps_triangles_facing_out()
+{
+    // Identity rows with the third one negated (bit 6)
+    let (in_data, scene) = voxport::load_scene_bytes(&scene_file("68")).unwrap();
+    let instances = voxport::convert_instances(&in_data, &scene, &MeshOptions::default()).unwrap();
+
+    let (second, triangles) = &instances[1];
+    assert!(second.transform.is_mirror());
+    assert_eq!(bounds(triangles), ([-1.0, 0.0, 4.0], [1.0, 1.0, 5.0]));
+    assert_winding_matches_normal(triangles);
+}
+
+#[test]
+fn mirrored_models_keep_their_obj_quads()
+{
+    let (in_data, scene) = voxport::load_scene_bytes(&scene_file("68")).unwrap();
+    let mut obj: Vec<u8> = Vec::new();
+    let mut mtl: Vec<u8> = Vec::new();
+    voxport::convert_vox_obj(&in_data, &scene, &MeshOptions::default(), &mut obj, &mut mtl, "scene.mtl").unwrap();
+
+    let faces: Vec<usize> = String::from_utf8(obj).unwrap().lines()
+        .filter(|line| line.starts_with("f "))
+        .map(|line| line.split_whitespace().count() - 1)
+        .collect();
+    assert_eq!(faces, vec![4; 12]);
+}
+
+#[test]
+fn scene_graph_is_mirrored_as_nodes()
+{
+    let (_, scene) = voxport::load_scene_bytes(&scene_file("4")).unwrap();
+    assert_eq!(scene.root.model, None);
+    assert_eq!(scene.root.children.len(), 2);
+    assert_eq!(scene.root.children[0].transform.translation, [10, 0, 0]);
+    assert_eq!(scene.root.children[1].name.as_deref(), Some("arm"));
+    assert_eq!(scene.root.children[1].model, Some(1));
+}
+
+#[test]
+fn invalid_rotation_is_a_parse_error()
+{
+    match voxport::load_scene_bytes(&scene_file("5"))
+    {
+        Err(voxport::VoxportError::Parse(_)) => {},
+        other => panic!("expected a parse error, got {:?}", other.map(|_| ()))
+    }
+}
+
+#[test]
+fn split_by_model_and_node()
+{
+    let (_, scene) = voxport::load_scene_bytes(&scene_file("4")).unwrap();
+
+    let parts = scene.split(SplitMode::Model);
+    let names: Vec<&str> = parts.iter().map(|part| part.name.as_str()).collect();
+    assert_eq!(names, vec!["Voxel-0", "arm"]);
+    assert_eq!(parts[1].scene.root.transform, voxport::Transform::IDENTITY);
+
+    let parts = scene.split(SplitMode::Node);
+    assert_eq!(parts.len(), 1);
+    assert_eq!(parts[0].name, "arm");
+    assert_eq!(parts[0].scene.models().into_iter().collect::<Vec<_>>(), vec![1]);
+    assert_eq!(parts[0].scene.root.children[0].transform.translation, [0, 0, 5]);
+}
+
+#[test]
+fn selection_filters_models_layers_and_hidden_nodes()
+{
+    let (_, mut scene) = voxport::load_scene_bytes(&scene_file("4")).unwrap();
+    let models = |scene: &voxport::Scene| scene.models().into_iter().collect::<Vec<_>>();
+    assert_eq!(models(&scene.select(&Selection::default())), vec![0, 1]);
+
+    let selection = Selection {
+        models: vec![1..=3],
+        ..Selection::default()
+    };
+    assert_eq!(models(&scene.select(&selection)), vec![1]);
+
+    let selection = Selection {
+        nodes: vec!["arm".to_string()],
+        ..Selection::default()
+    };
+    assert_eq!(models(&scene.select(&selection)), vec![1]);
+
+    // Every shape of the test file sits on layer 0
+    scene.layers.push(Layer { id: 0, name: Some("helpers".to_string()), hidden: false });
+    let selection = Selection {
+        layers: vec!["reference".to_string()],
+        ..Selection::default()
+    };
+    assert!(models(&scene.select(&selection)).is_empty());
+    let selection = Selection {
+        layers: vec!["helpers".to_string()],
+        ..Selection::default()
+    };
+    assert_eq!(models(&scene.select(&selection)), vec![0, 1]);
+
+    scene.layers[0].hidden = true;
+    assert!(models(&scene.select(&Selection::default())).is_empty());
+    let selection = Selection {
+        include_hidden: true,
+        ..Selection::default()
+    };
+    assert_eq!(models(&scene.select(&selection)), vec![0, 1]);
+
+    scene.layers[0].hidden = false;
+    scene.root.children[1].hidden = true;
+    assert_eq!(models(&scene.select(&Selection::default())), vec![0]);
+}
+
+#[test]
+fn animated_shapes_become_frames()
+{
+    // The first shape alternates between both models, the second holds still
+    let (_, scene) = voxport::load_scene_bytes(&build_file("4", &[0, 1, 0], &[1])).unwrap();
+    assert_eq!(scene.root.children[0].frames, vec![0, 1, 0]);
+
+    let frames = scene.frames();
+    assert_eq!(frames.len(), 3);
+    let shown: Vec<Vec<Option<usize>>> = frames.iter()
+        .map(|frame| frame.root.children.iter().map(|child| child.model).collect())
+        .collect();
+    assert_eq!(shown, vec![vec![Some(0), Some(1)], vec![Some(1), Some(1)], vec![Some(0), Some(1)]]);
+}
+
+#[test]
+fn models_become_frames_without_animated_shapes()
+{
+    let (_, scene) = voxport::load_scene_bytes(&scene_file("4")).unwrap();
+    let frames = scene.frames();
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[0].models().into_iter().collect::<Vec<_>>(), vec![0]);
+    assert_eq!(frames[1].models().into_iter().collect::<Vec<_>>(), vec![1]);
+    // Frames keep the placement of their model
+    assert_eq!(frames[1].root.children[0].transform.translation, [0, 0, 5]);
+}
+
+#[test]
+fn pivots_move_models_around_their_node()
+{
+    let (in_data, scene) = voxport::load_scene_bytes(&scene_file("4")).unwrap();
+    // The single voxel model, whose node is at x 10
+    let placed = |pivot: Pivot| {
+        let options = MeshOptions {
+            pivot,
+            ..MeshOptions::default()
+        };
+        let instances = voxport::convert_instances(&in_data, &scene, &options).unwrap();
+        return bounds(&instances[0].1);
+    };
+
+    assert_eq!(placed(Pivot::MagicaVoxel), ([10.0, 0.0, 0.0], [11.0, 1.0, 1.0]));
+    assert_eq!(placed(Pivot::Corner), ([10.0, 0.0, 0.0], [11.0, 1.0, 1.0]));
+    assert_eq!(placed(Pivot::Center), ([9.5, -0.5, -0.5], [10.5, 0.5, 0.5]));
+    assert_eq!(placed(Pivot::BottomCenter), ([9.5, -0.5, 0.0], [10.5, 0.5, 1.0]));
+    assert_eq!(placed(Pivot::Point([1.0, 0.0, 0.5])), ([9.0, 0.0, -0.5], [10.0, 1.0, 0.5]));
+
+    // MagicaVoxel rounds the center of the 2x1x1 model down, the box center doesn't
+    let options = MeshOptions {
+        pivot: Pivot::Center,
+        ..MeshOptions::default()
+    };
+    let triangles = voxport::convert_model(&in_data.models[1], &options).unwrap();
+    assert_eq!(bounds(&triangles), ([0.0, -0.5, -0.5], [2.0, 0.5, 0.5]));
+}
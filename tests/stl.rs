@@ -0,0 +1,157 @@
+#![allow(clippy::needless_return)]
+
+extern crate stl_io;
+extern crate voxport;
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::Cursor;
+
+use voxport::{Color, ColorGroup, MeshOptions, Mesher, StlColor, StlFormat};
+
+fn export(format: StlFormat) -> Vec<u8>
+{
+    let (in_data, scene) = voxport::load_scene("examples/shield.vox").unwrap();
+    let mut bytes: Vec<u8> = Vec::new();
+    voxport::convert_vox_stl(&in_data, &scene, &MeshOptions::default(), format, &mut bytes).unwrap();
+    return bytes;
+}
+
+fn bounds(triangles: &[voxport::Triangle]) -> ([f32; 3], [f32; 3])
+{
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for triangle in triangles
+    {
+        for vertex in [triangle.a, triangle.b, triangle.c].iter()
+        {
+            min = [min[0].min(vertex.x), min[1].min(vertex.y), min[2].min(vertex.z)];
+            max = [max[0].max(vertex.x), max[1].max(vertex.y), max[2].max(vertex.z)];
+        }
+    }
+    return (min, max);
+}
+
+/// Attribute fields of a binary STL
+fn attributes(bytes: &[u8]) -> Vec<u16>
+{
+    let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+    assert_eq!(bytes.len(), 84 + count * 50);
+    return (0..count)
+        .map(|i| {
+            let offset = 84 + i * 50 + 48;
+            u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+        })
+        .collect();
+}
+
+#[test]
+fn colors_follow_each_convention()
+{
+    let color = Color { r: 255, g: 128, b: 0, a: 255 };
+    assert_eq!(StlColor::VisCam.encode(color), 0x8000 | 31 << 10 | 16 << 5);
+    assert_eq!(StlColor::Materialise.encode(color), 16 << 5 | 31);
+}
+
+#[test]
+fn colored_facets_match_the_palette()
+{
+    let in_data = voxport::load("examples/shield.vox").unwrap();
+    let mut expected: Vec<u16> = in_data.models[0].voxels.iter()
+        .map(|voxel| StlColor::VisCam.encode(Color::from_palette(&in_data.palette, voxel.i)))
+        .collect();
+    expected.sort_unstable();
+    expected.dedup();
+
+    let bytes = export(StlFormat::ColorBinary(StlColor::VisCam));
+    let mut colors = attributes(&bytes);
+    colors.sort_unstable();
+    colors.dedup();
+    assert_eq!(colors, expected);
+
+    let bytes = export(StlFormat::ColorBinary(StlColor::Materialise));
+    assert!(bytes.starts_with(b"COLOR="));
+    assert!(attributes(&bytes).iter().all(|attribute| attribute & 0x8000 == 0));
+}
+
+#[test]
+fn every_format_holds_the_same_mesh()
+{
+    let read = |bytes: Vec<u8>| stl_io::read_stl(&mut Cursor::new(bytes)).unwrap();
+    let binary = read(export(StlFormat::Binary));
+    for format in [StlFormat::Ascii, StlFormat::ColorBinary(StlColor::Materialise)].iter()
+    {
+        let mesh = read(export(*format));
+        assert_eq!(mesh.faces.len(), binary.faces.len());
+        assert_eq!(mesh.vertices.len(), binary.vertices.len());
+    }
+}
+
+#[test]
+fn color_parts_are_closed_bodies_in_one_frame()
+{
+    let (in_data, scene) = voxport::load_scene("examples/shield.vox").unwrap();
+    let mut groups: BTreeMap<u8, usize> = BTreeMap::new();
+    groups.insert(14, 0);
+    groups.insert(149, 0);
+    let parts = voxport::split_by_color(&in_data, &scene, &groups);
+    assert_eq!(
+        parts.iter().map(|part| part.group).collect::<Vec<_>>(),
+        vec![ColorGroup::Group(0), ColorGroup::Index(151), ColorGroup::Index(159)]
+    );
+
+    let options = MeshOptions {
+        mesher: Mesher::Naive,
+        ..MeshOptions::default()
+    };
+    let whole = voxport::convert_vox(&in_data, &scene, &options).unwrap();
+    let mut voxels = 0;
+    let mut merged = ([f32::MAX; 3], [f32::MIN; 3]);
+    for part in &parts
+    {
+        voxels += part.in_data.models[0].voxels.len();
+        let triangles = voxport::convert_vox(&part.in_data, &scene, &options).unwrap();
+        // A closed surface runs along every edge once in each direction
+        let mut edges: HashMap<[i32; 6], i32> = HashMap::new();
+        for triangle in &triangles
+        {
+            let corners = [triangle.a, triangle.b, triangle.c];
+            for i in 0..3
+            {
+                let (from, to) = (corners[i], corners[(i + 1) % 3]);
+                let key = |a: voxport::Vertex, b: voxport::Vertex| [a.x as i32, a.y as i32, a.z as i32, b.x as i32, b.y as i32, b.z as i32];
+                *edges.entry(key(from, to)).or_default() += 1;
+                *edges.entry(key(to, from)).or_default() -= 1;
+            }
+        }
+        assert!(edges.values().all(|count| *count == 0), "part {:?} is not closed", part.group);
+
+        let (min, max) = bounds(&triangles);
+        for axis in 0..3
+        {
+            merged.0[axis] = merged.0[axis].min(min[axis]);
+            merged.1[axis] = merged.1[axis].max(max[axis]);
+        }
+    }
+    assert_eq!(voxels, in_data.models[0].voxels.len());
+    // Together the parts span the whole model, so none of them moved
+    assert_eq!(merged, bounds(&whole));
+}
+
+#[test]
+fn group_numbers_never_merge_with_unmapped_indices()
+{
+    let (in_data, scene) = voxport::load_scene("examples/shield.vox").unwrap();
+    let count = |index: u8| in_data.models[0].voxels.iter().filter(|voxel| voxel.i == index).count();
+    // Group 151 shares its number with a palette index the mapping leaves out
+    let mut groups: BTreeMap<u8, usize> = BTreeMap::new();
+    groups.insert(14, 151);
+    groups.insert(149, 151);
+    let parts = voxport::split_by_color(&in_data, &scene, &groups);
+
+    let sizes: Vec<(ColorGroup, usize)> = parts.iter().map(|part| (part.group, part.in_data.models[0].voxels.len())).collect();
+    assert_eq!(sizes, vec![
+        (ColorGroup::Group(151), count(14) + count(149)),
+        (ColorGroup::Index(151), count(151)),
+        (ColorGroup::Index(159), count(159))
+    ]);
+}
@@ -0,0 +1,110 @@
+#![allow(clippy::needless_return)]
+
+extern crate dot_vox;
+extern crate png;
+extern crate voxport;
+
+use voxport::{MeshOptions, Mesher, PaletteTexture, Scene, TextureLayout};
+
+fn options(layout: TextureLayout) -> MeshOptions
+{
+    return MeshOptions {
+        texture: Some(PaletteTexture {
+            layout,
+            uri: "palette.png".to_string()
+        }),
+        ..MeshOptions::default()
+    };
+}
+
+/// Decodes a PNG into its size and RGBA pixels
+fn decode(bytes: &[u8]) -> ((u32, u32), Vec<u8>)
+{
+    let mut reader = png::Decoder::new(bytes).read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    assert_eq!(info.color_type, png::ColorType::Rgba);
+    pixels.truncate(info.buffer_size());
+    return ((info.width, info.height), pixels);
+}
+
+#[test]
+fn texels_hold_their_palette_entry()
+{
+    let in_data = voxport::load("examples/shield.vox").unwrap();
+    for layout in [TextureLayout::Strip, TextureLayout::Grid].iter()
+    {
+        let mut bytes: Vec<u8> = Vec::new();
+        voxport::export_palette_texture(&in_data, *layout, &mut bytes).unwrap();
+        let (size, pixels) = decode(&bytes);
+        assert_eq!(size, layout.size());
+
+        for index in [0u8, 17, 200, 255].iter()
+        {
+            // The coordinates point at the center of the texel
+            let uv = layout.uv(*index);
+            let column = (uv[0] * size.0 as f32) as u32;
+            let row = (uv[1] * size.1 as f32) as u32;
+            assert_eq!((column, row), layout.texel(*index));
+
+            let offset = ((row * size.0 + column) * 4) as usize;
+            let color = voxport::Color::from_palette(&in_data.palette, *index);
+            assert_eq!(&pixels[offset..offset + 4], &[color.r, color.g, color.b, color.a]);
+        }
+    }
+}
+
+#[test]
+fn obj_faces_point_at_their_texel()
+{
+    let mut in_data = voxport::load("examples/shield.vox").unwrap();
+    in_data.models = vec! {
+        dot_vox::Model {
+            size: dot_vox::Size { x: 2, y: 1, z: 1 },
+            voxels: vec! {
+                dot_vox::Voxel { x: 0, y: 0, z: 0, i: 3 },
+                dot_vox::Voxel { x: 1, y: 0, z: 0, i: 40 }
+            }
+        }
+    };
+    let scene = Scene::from_models(&in_data);
+
+    let mut obj: Vec<u8> = Vec::new();
+    let mut mtl: Vec<u8> = Vec::new();
+    voxport::convert_vox_obj(&in_data, &scene, &options(TextureLayout::Grid), &mut obj, &mut mtl, "out.mtl").unwrap();
+    let obj = String::from_utf8(obj).unwrap();
+    let mtl = String::from_utf8(mtl).unwrap();
+
+    let mut uvs: Vec<Vec<f32>> = obj.lines()
+        .filter_map(|line| line.strip_prefix("vt "))
+        .map(|line| line.split_whitespace().map(|value| value.parse().unwrap()).collect())
+        .collect();
+    uvs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    // OBJ counts v from the bottom, so index 40 in row 2 of the grid is the third texel from the top
+    assert_eq!(uvs, vec![vec![3.5 / 16.0, 1.0 - 0.5 / 16.0], vec![8.5 / 16.0, 1.0 - 2.5 / 16.0]]);
+
+    for face in obj.lines().filter(|line| line.starts_with("f "))
+    {
+        let corners: Vec<&str> = face.split_whitespace().skip(1).collect();
+        assert_eq!(corners.len(), 4);
+        assert!(corners.iter().all(|corner| corner.split('/').nth(1) == corners[0].split('/').nth(1)));
+    }
+    assert_eq!(obj.matches("usemtl").count(), 1);
+    assert!(mtl.contains("map_Kd palette.png"));
+}
+
+#[test]
+fn merged_colors_cannot_be_textured()
+{
+    let (in_data, scene) = voxport::load_scene("examples/shield.vox").unwrap();
+    let options = MeshOptions {
+        mesher: Mesher::Greedy { merge_colors: true },
+        ..options(TextureLayout::Strip)
+    };
+    let mut bytes: Vec<u8> = Vec::new();
+    match voxport::convert_vox_glb(&in_data, &scene, &options, &mut bytes)
+    {
+        Err(voxport::VoxportError::Export(_)) => {},
+        other => panic!("expected an export error, got {:?}", other)
+    }
+}
@@ -0,0 +1,82 @@
+#![allow(clippy::needless_return)]
+
+extern crate serde_json;
+extern crate stl_io;
+extern crate voxport;
+
+use std::io::Cursor;
+
+use voxport::{MeshOptions, Unit, VoxelSize};
+
+fn options(length: f32, unit: Unit) -> MeshOptions
+{
+    return MeshOptions {
+        voxel_size: Some(VoxelSize { length, unit }),
+        ..MeshOptions::default()
+    };
+}
+
+/// Smallest and largest corner of the mesh in an STL file
+fn stl_bounds(options: &MeshOptions) -> ([f32; 3], [f32; 3])
+{
+    let (in_data, scene) = voxport::load_scene("examples/shield.vox").unwrap();
+    let mut bytes: Vec<u8> = Vec::new();
+    voxport::convert_vox_stl(&in_data, &scene, options, voxport::StlFormat::Binary, &mut bytes).unwrap();
+    let mesh = stl_io::read_stl(&mut Cursor::new(bytes)).unwrap();
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for vertex in &mesh.vertices
+    {
+        for axis in 0..3
+        {
+            min[axis] = min[axis].min(vertex[axis]);
+            max[axis] = max[axis].max(vertex[axis]);
+        }
+    }
+    return (min, max);
+}
+
+#[test]
+fn voxel_size_scales_positions_in_its_unit()
+{
+    let (min, max) = stl_bounds(&MeshOptions::default());
+    let (scaled_min, scaled_max) = stl_bounds(&options(0.5, Unit::Millimeter));
+    for axis in 0..3
+    {
+        assert_eq!(scaled_min[axis], min[axis] * 0.5);
+        assert_eq!(scaled_max[axis], max[axis] * 0.5);
+    }
+}
+
+#[test]
+fn collada_names_the_unit()
+{
+    let (in_data, scene) = voxport::load_scene("examples/shield.vox").unwrap();
+    let mut bytes: Vec<u8> = Vec::new();
+    voxport::convert_vox_dae(&in_data, &scene, &options(2.0, Unit::Centimeter), &mut bytes).unwrap();
+    let document = String::from_utf8(bytes).unwrap();
+    assert!(document.contains("<unit name=\"centimeter\" meter=\"0.01\" />"), "{}", document);
+    // The shield model is placed at z 8 and moved down by its pivot
+    assert!(document.contains("<matrix sid=\"transform\">1 0 0 0 0 1 0 -2 0 0 1 16 0 0 0 1</matrix>"));
+}
+
+#[test]
+fn gltf_positions_are_meters()
+{
+    let (in_data, scene) = voxport::load_scene("examples/shield.vox").unwrap();
+    let position_bounds = |options: &MeshOptions| -> Vec<f64> {
+        let mut json: Vec<u8> = Vec::new();
+        let mut bin: Vec<u8> = Vec::new();
+        voxport::convert_vox_gltf(&in_data, &scene, options, &mut json, &mut bin, "shield.bin").unwrap();
+        let document: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        let accessor = &document["accessors"][0];
+        return accessor["max"].as_array().unwrap().iter().map(|value| value.as_f64().unwrap()).collect();
+    };
+
+    let voxels = position_bounds(&MeshOptions::default());
+    let meters = position_bounds(&options(5.0, Unit::Millimeter));
+    for (voxel, meter) in voxels.iter().zip(&meters)
+    {
+        assert!((voxel * 0.005 - meter).abs() < 1e-6, "{} voxels came out as {} m", voxel, meter);
+    }
+}
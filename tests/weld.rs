@@ -0,0 +1,58 @@
+#![allow(clippy::needless_return)]
+
+extern crate dot_vox;
+extern crate voxport;
+
+use voxport::mesh::{convert_meta_voxels, convert_triangles, index_triangles, reorder_voxels};
+use voxport::weld::Welder;
+use voxport::{IndexedTriangle, Normal, Vertex};
+
+#[test]
+fn welded_triangles_expand_to_the_unwelded_ones()
+{
+    for path in ["examples/shield.vox", "examples/column.vox"].iter()
+    {
+        let in_data = voxport::load(path).unwrap();
+        for model in &in_data.models
+        {
+            let voxels = reorder_voxels(&model.voxels, &model.size).unwrap();
+            let triangles = convert_triangles(&convert_meta_voxels(&voxels, &model.size));
+
+            let mut vertices: Vec<Vertex> = Vec::new();
+            let mut normals: Vec<Normal> = Vec::new();
+            let mut idx_triangles: Vec<IndexedTriangle> = Vec::new();
+            index_triangles(&triangles, &mut vertices, &mut normals, &mut idx_triangles);
+
+            assert_eq!(idx_triangles.len(), triangles.len());
+            assert!(vertices.len() < triangles.len() * 3);
+            assert!(normals.len() <= 6);
+            for (indexed, triangle) in idx_triangles.iter().zip(triangles.iter())
+            {
+                assert_eq!(vertices[indexed.a], triangle.a);
+                assert_eq!(vertices[indexed.b], triangle.b);
+                assert_eq!(vertices[indexed.c], triangle.c);
+                assert_eq!(normals[indexed.normal_index], triangle.normal);
+                assert_eq!(indexed.normal, triangle.normal);
+                assert_eq!(indexed.color_index, triangle.color_index);
+            }
+        }
+    }
+}
+
+#[test]
+fn existing_lists_keep_their_indices()
+{
+    let vertex = |x: f32| Vertex { x, y: 0.0, z: 0.0 };
+    // The second vertex is within welding distance of the first
+    let vertices = vec! { vertex(0.0), vertex(0.0001), vertex(1.0) };
+    let normals = vec! { Normal { x: 0.0, y: 0.0, z: 1.0 } };
+
+    let mut welder = Welder::from_lists(vertices.clone(), normals.clone());
+    assert_eq!(welder.vertices, vertices);
+    assert_eq!(welder.normals, normals);
+    assert_eq!(welder.vertex(vertex(1.0)), 2);
+    assert_eq!(welder.vertex(vertex(0.0)), 0);
+    assert_eq!(welder.vertex(vertex(2.0)), 3);
+    assert_eq!(welder.normal(Normal { x: 0.0, y: 0.0, z: 1.0 }), 0);
+    assert_eq!(welder.normal(Normal { x: 1.0, y: 0.0, z: 0.0 }), 1);
+}
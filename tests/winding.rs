@@ -0,0 +1,89 @@
+#![allow(clippy::needless_return)]
+
+extern crate dot_vox;
+extern crate voxport;
+
+use voxport::mesh::{get_voxel_idx, reorder_voxels};
+use voxport::{MeshOptions, Mesher, Triangle, Vertex};
+
+const EXAMPLES: [&str; 2] = ["examples/shield.vox", "examples/column.vox"];
+
+const MESHERS: [Mesher; 4] = [
+    Mesher::Naive,
+    Mesher::Greedy { merge_colors: false },
+    Mesher::Greedy { merge_colors: true },
+    Mesher::Manifold
+];
+
+fn sub(a: Vertex, b: Vertex) -> [f32; 3]
+{
+    return [a.x - b.x, a.y - b.y, a.z - b.z];
+}
+
+fn cross(u: [f32; 3], v: [f32; 3]) -> [f32; 3]
+{
+    return [
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0]
+    ];
+}
+
+fn for_each_triangle<F: FnMut(&dot_vox::Model, &Triangle)>(mut f: F)
+{
+    for path in EXAMPLES.iter()
+    {
+        let in_data = voxport::load(path).unwrap();
+        for mesher in MESHERS.iter()
+        {
+            let options = MeshOptions {
+                mesher: *mesher,
+                ..MeshOptions::default()
+            };
+            for model in &in_data.models
+            {
+                for triangle in voxport::convert_model(model, &options).unwrap()
+                {
+                    f(model, &triangle);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn winding_matches_normal()
+{
+    for_each_triangle(|_, triangle| {
+        let winding = cross(sub(triangle.b, triangle.a), sub(triangle.c, triangle.a));
+        let length = (winding[0] * winding[0] + winding[1] * winding[1] + winding[2] * winding[2]).sqrt();
+        assert!(length > 0.0, "degenerate triangle {:?}", triangle);
+
+        let normal = triangle.normal;
+        let dot = (winding[0] * normal.x + winding[1] * normal.y + winding[2] * normal.z) / length;
+        assert!(dot > 0.999, "winding of {:?} disagrees with its normal", triangle);
+    });
+}
+
+#[test]
+fn normals_point_out_of_the_model()
+{
+    for_each_triangle(|model, triangle| {
+        let voxels = reorder_voxels(&model.voxels, &model.size).unwrap();
+        let is_filled = |offset: f32| {
+            let x = (triangle.a.x + triangle.b.x + triangle.c.x) / 3.0 + triangle.normal.x * offset;
+            let y = (triangle.a.y + triangle.b.y + triangle.c.y) / 3.0 + triangle.normal.y * offset;
+            let z = (triangle.a.z + triangle.b.z + triangle.c.z) / 3.0 + triangle.normal.z * offset;
+            if x < 0.0 || y < 0.0 || z < 0.0 ||
+               x >= model.size.x as f32 || y >= model.size.y as f32 || z >= model.size.z as f32
+            {
+                return false;
+            }
+            let pos = dot_vox::Voxel { x: x as u8, y: y as u8, z: z as u8, i: 0 };
+            return voxels[get_voxel_idx(&pos, &model.size)].is_some();
+        };
+
+        assert!(is_filled(-0.5), "no voxel behind {:?}", triangle);
+        assert!(!is_filled(0.5), "voxel in front of {:?}", triangle);
+    });
+}